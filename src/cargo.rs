@@ -1,21 +1,25 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
-use alloc::{borrow::ToOwned as _, format, string::String, vec::Vec};
-use std::path::{Path, PathBuf};
+use alloc::{borrow::ToOwned as _, format, string::String, vec, vec::Vec};
+use std::{
+    env, eprint, eprintln, fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Context as _, Result};
 use build_context::{CARGO, RUSTC};
 pub(crate) use cargo_config2::Config;
+use cargo_config2::cfg::{TargetAbi, TargetArch};
 use serde_derive::Deserialize;
 
-use crate::RevisionContext;
+use crate::{ArchFamily, RevisionContext, process::ProcessBuilder};
 
 pub(crate) fn locate_project(manifest_path: &Path) -> Result<String> {
     cmd!(CARGO, "locate-project", "--message-format", "plain", "--manifest-path", manifest_path)
         .read()
 }
 
-pub(crate) fn metadata(manifest_path: &str) -> Result<Metadata> {
+pub(crate) fn metadata(manifest_path: &str, target_dir: Option<&Path>) -> Result<Metadata> {
     let mut cmd = cmd!(
         CARGO,
         "metadata",
@@ -24,30 +28,167 @@ pub(crate) fn metadata(manifest_path: &str) -> Result<Metadata> {
         "--manifest-path",
         manifest_path
     );
+    if let Some(target_dir) = target_dir {
+        cmd.env("CARGO_TARGET_DIR", target_dir);
+    }
     serde_json::from_str(&cmd.read()?).with_context(|| format!("failed to parse output from {cmd}"))
 }
 
-pub(crate) fn config(manifest_dir: &Path) -> Result<Config, cargo_config2::Error> {
-    Config::load_with_options(
-        manifest_dir,
-        cargo_config2::ResolveOptions::default()
-            .rustc(cargo_config2::PathAndArgs::new(RUSTC))
-            .cargo(CARGO)
-            .cargo_home(None)
-            .host_triple(build_context::HOST),
-    )
+pub(crate) fn config(
+    manifest_dir: &Path,
+    host_triple: Option<&str>,
+    reproducible: bool,
+) -> Result<Config, cargo_config2::Error> {
+    let mut options = cargo_config2::ResolveOptions::default()
+        .rustc(cargo_config2::PathAndArgs::new(RUSTC))
+        .cargo(CARGO)
+        .cargo_home(None)
+        .host_triple(host_triple.unwrap_or(build_context::HOST));
+    if reproducible {
+        options = options.env(env::vars_os().filter(|(k, _)| !k.to_str().is_some_and(is_scrubbed)));
+    }
+    Config::load_with_options(manifest_dir, options)
+}
+
+/// Returns `true` if the given environment variable is ignored by `Tester::reproducible`.
+///
+/// Variables that affect code generation or the layout of the generated files but are
+/// usually set in the shell of the developer (e.g., `RUSTFLAGS`, `CARGO_PROFILE_RELEASE_LTO`).
+/// Variables that only affect where the files are placed (e.g., `CARGO_TARGET_DIR`) are kept.
+fn is_scrubbed(name: &str) -> bool {
+    matches!(
+        name,
+        "RUSTFLAGS"
+            | "CARGO_ENCODED_RUSTFLAGS"
+            | "CARGO_BUILD_RUSTFLAGS"
+            | "CARGO_INCREMENTAL"
+            | "CARGO_BUILD_INCREMENTAL"
+            | "CARGO_BUILD_TARGET"
+            | "RUSTC_WRAPPER"
+            | "RUSTC_WORKSPACE_WRAPPER"
+            | "CARGO_BUILD_RUSTC_WRAPPER"
+            | "CARGO_BUILD_RUSTC_WORKSPACE_WRAPPER"
+    ) || name.starts_with("CARGO_PROFILE_")
+        || name.starts_with("CARGO_TARGET_") && name.ends_with("_RUSTFLAGS")
+}
+
+pub(crate) fn rustc_verbose_version(rustc: &Path) -> Result<String> {
+    cmd!(rustc, "-vV").read()
 }
 
+pub(crate) fn is_nightly(rustc: &Path) -> Result<bool> {
+    let verbose_version = rustc_verbose_version(rustc)?;
+    let release = verbose_version
+        .lines()
+        .find_map(|line| line.strip_prefix("release: "))
+        .with_context(|| format!("failed to parse output from `{} -vV`", rustc.display()))?;
+    Ok(release.contains("-nightly") || release.contains("-dev"))
+}
+
+/// Builds the crate, and returns `false` if the build failed with diagnostics captured by
+/// `Tester::capture_diagnostics`.
 pub(crate) fn build(
     cx: &mut RevisionContext<'_>,
     cargo_base_args: &[&str],
     cargo_base_rest_args: &[&str],
-) {
-    let mut rustflags = cx.tcx.config.rustflags(&cx.revision.target).unwrap().unwrap_or_default();
+) -> bool {
+    if cx.revision.assert_soft_float {
+        check_soft_float(cx);
+    }
+    if cx.tcx.tester.prebuilt {
+        let package = cx
+            .tcx
+            .metadata
+            .packages
+            .iter()
+            .find(|p| p.manifest_path == cx.tcx.manifest_path)
+            .with_context(|| format!("not found package for {}", cx.tcx.manifest_path))
+            .unwrap();
+        cx.obj_path = locate_object(cx, &package.name, None);
+        return true;
+    }
+    // Note: Profile-specific rustflags (`[profile.<name>] rustflags`, unstable `profile-rustflags`
+    // feature) are not included here because Cargo passes them to rustc in addition to
+    // CARGO_ENCODED_RUSTFLAGS, so merging them here would duplicate them.
+    // CARGO_ENCODED_RUSTFLAGS takes precedence over RUSTFLAGS and the Cargo configuration,
+    // so starting from empty flags ignores them.
+    let mut rustflags = if cx.enabled(|c| c.clean_rustflags) {
+        cargo_config2::Flags::default()
+    } else {
+        cx.tcx.config.rustflags(&cx.revision.target).unwrap().unwrap_or_default()
+    };
+    // Instrumentation for coverage or PGO (e.g., enabled by cargo-llvm-cov) fills the assembly
+    // with counter updates.
+    let mut i = 0;
+    while i < rustflags.flags.len() {
+        let (len, flag) = match (&*rustflags.flags[i], rustflags.flags.get(i + 1)) {
+            ("-C" | "-Z", Some(next)) => (2, &**next),
+            (f, _) => (1, f.strip_prefix("-C").or_else(|| f.strip_prefix("-Z")).unwrap_or(f)),
+        };
+        if is_instrumentation_flag(flag) {
+            if cx.tcx.tester.reproducible {
+                rustflags.flags.drain(i..i + len);
+                continue;
+            }
+            eprintln!(
+                "warning: `{}` is enabled in rustflags, which adds instrumentation to the generated assembly; use `Tester::reproducible` or `Tester::clean_rustflags` to ignore it",
+                rustflags.flags[i..i + len].join(" ")
+            );
+        }
+        i += len;
+    }
     rustflags.push("-Z");
     rustflags.push("merge-functions=disabled");
     rustflags.flags.extend_from_slice(&cx.tcx.tester.config.rustc_args);
     rustflags.flags.extend_from_slice(&cx.revision.config.rustc_args);
+    rustflags.flags.extend_from_slice(&cx.tcx.tester.config.codegen_args);
+    rustflags.flags.extend_from_slice(&cx.revision.config.codegen_args);
+    if let Some(opt_level) =
+        cx.revision.config.opt_level.as_ref().or(cx.tcx.tester.config.opt_level.as_ref())
+    {
+        // This overrides -C opt-level passed by Cargo based on the profile.
+        rustflags.push("-C");
+        rustflags.push(format!("opt-level={opt_level}"));
+    }
+    if let Some(panic) = cx.revision.config.panic.or(cx.tcx.tester.config.panic) {
+        rustflags.push("-C");
+        rustflags.push(format!("panic={}", panic.as_str()));
+    }
+    // With multiple codegen units, rustc emits an object file per codegen unit and doesn't
+    // emit the object file we read, so force a single codegen unit. This also overrides
+    // -C codegen-units passed by Cargo based on the profile.
+    if let Some(codegen_units) = rustflags
+        .flags
+        .iter()
+        .filter_map(|f| f.strip_prefix("-C").unwrap_or(f).strip_prefix("codegen-units="))
+        .next_back()
+    {
+        if codegen_units != "1" {
+            eprintln!(
+                "warning: ignoring `-C codegen-units={codegen_units}`; asmtest always uses a single codegen unit"
+            );
+        }
+    }
+    // The value in the profile of Cargo.toml or Cargo config can't be checked without parsing
+    // them, so it is only mentioned in the documentation of Tester::codegen_args.
+    if let Some(codegen_units) = env::var("CARGO_PROFILE_RELEASE_CODEGEN_UNITS")
+        .ok()
+        .filter(|n| n != "1" && !cx.tcx.tester.reproducible)
+    {
+        eprintln!(
+            "warning: ignoring `CARGO_PROFILE_RELEASE_CODEGEN_UNITS={codegen_units}`; asmtest always uses a single codegen unit"
+        );
+    }
+    rustflags.push("-C");
+    rustflags.push("codegen-units=1");
+    if let Some(target_cpu) = &cx.revision.target_cpu {
+        rustflags.push("-C");
+        rustflags.push(format!("target-cpu={target_cpu}"));
+    }
+    if !cx.revision.target_features.is_empty() {
+        rustflags.push("-C");
+        rustflags.push(format!("target-feature={}", cx.revision.target_features.join(",")));
+    }
     let rustflags = &rustflags.encode().unwrap();
     let mut args = cargo_base_args.to_owned();
     args.push("--target");
@@ -63,25 +204,74 @@ pub(crate) fn build(
             }
         }
     }
-    let mut cargo = cmd!(CARGO);
-    if !cx.tcx.nightly {
-        // We set -Z merge-functions=disabled to rustc.
-        cargo.env("RUSTC_BOOTSTRAP", "1");
+    if cx.tcx.tester.clean {
+        let package = cx
+            .tcx
+            .metadata
+            .packages
+            .iter()
+            .find(|p| p.manifest_path == cx.tcx.manifest_path)
+            .with_context(|| format!("not found package for {}", cx.tcx.manifest_path))
+            .unwrap();
+        let res = cargo(cx)
+            .args(["clean", "--release", "--package", &package.name, "--target"])
+            .arg(&cx.revision.target)
+            .args(["--manifest-path", &cx.tcx.manifest_path])
+            .run();
+        cx.check_timeout(&res);
+        res.unwrap();
     }
-    let Ok(json) = cargo
+    let mut diagnostics = cx.diagnostics.take();
+    let workspace_root = format!("{}/", cx.tcx.metadata.workspace_root.display());
+    let res = cargo(cx)
         .args(&args)
         .arg("--message-format=json")
         .args(&rest_args)
         .env("CARGO_ENCODED_RUSTFLAGS", rustflags)
-        .read()
-    else {
+        .read_with_progress(|line| {
+            // Show progress so that long builds (e.g., with build-std) don't look hung.
+            let Ok(msg) = serde_json::from_str::<Message>(line) else { return };
+            match &*msg.reason {
+                "compiler-artifact" | "build-script-executed" => {
+                    if let Some(package_id) = msg.package_id {
+                        let name = package_name(&package_id);
+                        if msg.reason == "compiler-artifact" {
+                            eprintln!("    compiled {name}");
+                        } else {
+                            eprintln!("    ran build script of {name}");
+                        }
+                    }
+                }
+                "compiler-message" => {
+                    let Some(rendered) = msg.message.and_then(|m| m.rendered) else { return };
+                    if cx.tcx.tester.show_warnings {
+                        eprint!("{rendered}");
+                    }
+                    if let Some(diagnostics) = &mut diagnostics {
+                        if msg.manifest_path.as_deref() == Some(&*cx.tcx.manifest_path) {
+                            // Paths are relative to the workspace root for packages in the
+                            // workspace, but normalize absolute paths just in case.
+                            diagnostics.push_str(&rendered.replace(&workspace_root, ""));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        });
+    cx.check_timeout(&res);
+    let failed_with_diagnostics =
+        res.is_err() && diagnostics.as_ref().is_some_and(|d| !d.is_empty());
+    cx.diagnostics = diagnostics;
+    if failed_with_diagnostics {
+        return false;
+    }
+    let Ok(json) = res else {
+        check_merge_functions(cx);
         // Show error from Cargo to the user.
-        let mut cargo = cmd!(CARGO);
-        if !cx.tcx.nightly {
-            // We set -Z merge-functions=disabled to rustc.
-            cargo.env("RUSTC_BOOTSTRAP", "1");
-        }
-        cargo.args(&args).args(&rest_args).env("CARGO_ENCODED_RUSTFLAGS", rustflags).run().unwrap();
+        let res =
+            cargo(cx).args(&args).args(&rest_args).env("CARGO_ENCODED_RUSTFLAGS", rustflags).run();
+        cx.check_timeout(&res);
+        res.unwrap();
         unreachable!()
     };
     let mut hash = None;
@@ -98,46 +288,233 @@ pub(crate) fn build(
         }
         for filename in &artifact.filenames {
             if let Some(f) = filename.strip_suffix(".rmeta") {
-                if let Some((_, h)) = f.rsplit_once('-') {
-                    hash = Some((h.to_owned(), artifact));
-                    break 'hash;
-                }
+                // e.g., lib<crate name>-<hash>.rmeta
+                let Some((_, h)) = f.rsplit_once('-') else {
+                    panic!(
+                        "failed to extract hash from file name of artifact `{filename}` for {}; \
+                         the naming of artifacts may have been changed in Cargo",
+                        cx.tcx.manifest_path
+                    );
+                };
+                hash = Some((h.to_owned(), artifact));
+                break 'hash;
             }
         }
     }
     let Some((hash, artifact)) = hash else {
         panic!("not found .rmeta file in artifacts for {}", cx.tcx.manifest_path);
     };
-    // TODO: search both?
-    cx.obj_path = cx
+    cx.obj_path = locate_object(cx, package_name(&artifact.package_id), Some(&hash));
+    true
+}
+
+/// Returns the path to the object file (or LLVM IR file if `Tester::emit_llvm_ir` is set)
+/// of the given package in the deps directories.
+///
+/// If `hash` is `None` (i.e., the crate was built by another tool), the most recently
+/// modified file of the package is used.
+fn locate_object(cx: &RevisionContext<'_>, package_name: &str, hash: Option<&str>) -> PathBuf {
+    // With --emit=llvm-ir, there is no object file, so search the IR file instead.
+    let ext = if cx.tcx.tester.emit_llvm_ir { "ll" } else { "o" };
+    let crate_name = package_name.replace('-', "_");
+    // The object is usually placed in the build directory if it is set, but it may be placed in
+    // the target directory depending on the toolchain and configuration, so search both.
+    let mut deps_dirs: Vec<PathBuf> = cx
         .tcx
         .metadata
         .build_directory
-        .as_ref()
-        .unwrap_or(&cx.tcx.metadata.target_directory)
-        .canonicalize()
-        .expect("failed to canonicalize target directory")
-        .join(cx.target_name)
-        .join("release/deps")
-        .join(format!(
-            "{}-{hash}.o",
-            Path::new(&artifact.package_id)
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .split_once('#')
-                .context(artifact.package_id.clone())
-                .unwrap()
-                .0
-                .replace('-', "_")
-        ));
+        .iter()
+        .chain([&cx.tcx.metadata.target_directory])
+        .map(|dir| dir.join(cx.target_name).join("release/deps"))
+        .collect();
+    deps_dirs.dedup();
+    let obj_path = match hash {
+        Some(hash) => {
+            let obj_name = format!("{crate_name}-{hash}.{ext}");
+            let candidates: Vec<PathBuf> = deps_dirs.iter().map(|d| d.join(&obj_name)).collect();
+            let Some(obj_path) = candidates.iter().find(|p| p.is_file()) else {
+                panic!("not found object file; searched {candidates:?}");
+            };
+            obj_path.clone()
+        }
+        None => {
+            let mut latest = None;
+            for dir in &deps_dirs {
+                let Ok(entries) = fs::read_dir(dir) else { continue };
+                for entry in entries {
+                    let entry = entry.unwrap();
+                    let file_name = entry.file_name();
+                    let Some(h) = file_name
+                        .to_str()
+                        .and_then(|f| f.strip_prefix(&crate_name)?.strip_prefix('-'))
+                        .and_then(|f| f.strip_suffix(ext)?.strip_suffix('.'))
+                    else {
+                        continue;
+                    };
+                    if h.is_empty() || !h.bytes().all(|b| b.is_ascii_hexdigit()) {
+                        continue;
+                    }
+                    let modified = entry.metadata().unwrap().modified().unwrap();
+                    if latest.as_ref().is_none_or(|(m, _)| modified > *m) {
+                        latest = Some((modified, entry.path()));
+                    }
+                }
+            }
+            let Some((_, obj_path)) = latest else {
+                panic!(
+                    "not found prebuilt object file `{crate_name}-<hash>.{ext}`; searched {deps_dirs:?}"
+                );
+            };
+            obj_path
+        }
+    };
+    obj_path.canonicalize().unwrap()
+}
+
+fn is_instrumentation_flag(flag: &str) -> bool {
+    match flag.split_once('=') {
+        Some(("instrument-coverage", v)) => !matches!(v, "n" | "no" | "off" | "false"),
+        Some((name, _)) => name == "profile-generate",
+        None => matches!(flag, "instrument-coverage" | "profile-generate"),
+    }
+}
+
+/// Checks that the target uses a soft-float ABI. See `Revision::assert_soft_float`.
+fn check_soft_float(cx: &RevisionContext<'_>) {
+    let config = &cx.tcx.config;
+    let abi = config.cfg::<TargetAbi, _>(cx.target_name).unwrap();
+    let abi = abi.as_ref().map_or("", TargetAbi::as_str);
+    let features = || {
+        // cargo-config2 doesn't provide target features, so ask rustc.
+        let rustc = cx.revision.rustc.as_deref().unwrap_or(Path::new(RUSTC));
+        let mut args = vec![
+            rustc.to_string_lossy().into_owned(),
+            "--target".to_owned(),
+            cx.revision.target.clone(),
+        ];
+        if let Some(target_cpu) = &cx.revision.target_cpu {
+            args.push(format!("-Ctarget-cpu={target_cpu}"));
+        }
+        if !cx.revision.target_features.is_empty() {
+            args.push(format!("-Ctarget-feature={}", cx.revision.target_features.join(",")));
+        }
+        let mut cache = cx.tcx.target_features.borrow_mut();
+        if let Some((_, features)) = cache.iter().find(|(a, _)| *a == args) {
+            return features.clone();
+        }
+        let cfg = cmd!(rustc, "--print", "cfg").args(&args[1..]).read().unwrap();
+        let features: Vec<String> = cfg
+            .lines()
+            .filter_map(|l| {
+                Some(l.strip_prefix("target_feature=\"")?.strip_suffix('"')?.to_owned())
+            })
+            .collect();
+        cache.push((args, features.clone()));
+        features
+    };
+    let has = |features: &[String], f: &str| features.iter().any(|x| x == f);
+    let (soft, suggestion) = match cx.arch_family {
+        ArchFamily::Arm => (abi != "eabihf", "a `*eabi` target instead of `*eabihf`"),
+        ArchFamily::AArch64 => (abi == "softfloat", "`aarch64-unknown-none-softfloat`"),
+        ArchFamily::LoongArch => (abi == "softfloat", "`loongarch64-unknown-none-softfloat`"),
+        ArchFamily::CSky => (abi != "abiv2hf", "`csky-unknown-linux-gnuabiv2`"),
+        ArchFamily::RiscV => {
+            let features = features();
+            (
+                !has(&features, "f") && !has(&features, "d"),
+                "a target without F and D extensions (e.g., `riscv64imac-unknown-none-elf`)",
+            )
+        }
+        ArchFamily::X86 => {
+            let features = features();
+            let float =
+                if config.cfg::<TargetArch, _>(cx.target_name).unwrap() == TargetArch::x86_64 {
+                    "sse"
+                } else {
+                    "x87"
+                };
+            (!has(&features, float), "`x86_64-unknown-none`")
+        }
+        _ => {
+            eprintln!(
+                "warning: ignoring Revision::assert_soft_float for revision {}; it is not supported for the architecture of {}",
+                cx.revision.name, cx.revision.target
+            );
+            return;
+        }
+    };
+    if !soft {
+        panic!(
+            "revision {} is expected to use a soft-float ABI, but {} uses a hard-float ABI; use {suggestion}",
+            cx.revision.name, cx.revision.target
+        );
+    }
+}
+
+/// Checks that rustc accepts `-Z merge-functions=disabled`, to show a clear error instead of
+/// the build error if it is removed or renamed in the future.
+fn check_merge_functions(cx: &RevisionContext<'_>) {
+    let rustc = cx.revision.rustc.as_deref().unwrap_or(Path::new(RUSTC));
+    let mut cmd = cmd!(rustc, "-Z", "merge-functions=disabled", "--print", "sysroot");
+    if !cx.nightly {
+        cmd.env("RUSTC_BOOTSTRAP", "1");
+    }
+    if let Err(e) = cmd.read() {
+        panic!(
+            "{rustc} doesn't accept `-Z merge-functions=disabled`, which asmtest uses to prevent functions with identical code from being merged; please report this to asmtest\n{e:#}",
+            rustc = rustc.display()
+        );
+    }
+}
+
+/// Extracts the package name from a package ID specification (e.g., `path+file:///foo#0.1.0`,
+/// `registry+https://github.com/rust-lang/crates.io-index#bar@0.1.0`).
+fn package_name(package_id: &str) -> &str {
+    let Some((url, fragment)) = package_id.rsplit_once('#') else { return package_id };
+    match fragment.split_once('@') {
+        Some((name, _version)) => name,
+        None => url.rsplit('/').next().unwrap_or(url),
+    }
+}
+
+fn cargo(cx: &RevisionContext<'_>) -> ProcessBuilder {
+    let mut cargo = cmd!(CARGO);
+    cargo.deadline(cx.deadline);
+    // We pass rustflags via CARGO_ENCODED_RUSTFLAGS, which already includes RUSTFLAGS
+    // unless they are ignored.
+    cargo.env_remove("RUSTFLAGS");
+    if cx.tcx.tester.reproducible {
+        for (k, _) in env::vars_os() {
+            if k.to_str().is_some_and(is_scrubbed) {
+                cargo.env_remove(k);
+            }
+        }
+    }
+    if !cx.nightly {
+        // We set -Z merge-functions=disabled to rustc.
+        cargo.env("RUSTC_BOOTSTRAP", "1");
+    }
+    if let Some(rustc) = &cx.revision.rustc {
+        cargo.env("RUSTC", rustc);
+    }
+    if let Some(target_dir) = &cx.tcx.target_dir {
+        cargo.env("CARGO_TARGET_DIR", target_dir);
+    }
+    cargo
 }
 
 #[derive(Deserialize)]
 pub(crate) struct Metadata {
+    pub(crate) workspace_root: PathBuf,
     pub(crate) target_directory: PathBuf,
     pub(crate) build_directory: Option<PathBuf>,
+    pub(crate) packages: Vec<Package>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Package {
+    pub(crate) name: String,
+    pub(crate) manifest_path: String,
 }
 
 #[derive(Deserialize)]
@@ -146,3 +523,44 @@ struct Artifact {
     manifest_path: String,
     filenames: Vec<String>,
 }
+
+#[derive(Deserialize)]
+struct Message {
+    reason: String,
+    package_id: Option<String>,
+    manifest_path: Option<String>,
+    message: Option<Diagnostic>,
+}
+
+#[derive(Deserialize)]
+struct Diagnostic {
+    rendered: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(package_name("path+file:///foo/bar#0.1.0"), "bar");
+        assert_eq!(package_name("path+file:///foo/bar#baz@0.1.0"), "baz");
+        assert_eq!(
+            package_name("registry+https://github.com/rust-lang/crates.io-index#serde@1.0.0"),
+            "serde"
+        );
+    }
+
+    #[test]
+    fn test_is_instrumentation_flag() {
+        assert!(is_instrumentation_flag("instrument-coverage"));
+        assert!(is_instrumentation_flag("instrument-coverage=yes"));
+        assert!(is_instrumentation_flag("instrument-coverage=all"));
+        assert!(!is_instrumentation_flag("instrument-coverage=n"));
+        assert!(!is_instrumentation_flag("instrument-coverage=off"));
+        assert!(is_instrumentation_flag("profile-generate"));
+        assert!(is_instrumentation_flag("profile-generate=/tmp/pgo"));
+        assert!(!is_instrumentation_flag("profile-use=/tmp/pgo.profdata"));
+        assert!(!is_instrumentation_flag("opt-level=3"));
+    }
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Generates a crate that exposes `core::sync::atomic` operations as standalone functions,
+// to compare their assemblies with the ones of the crate being tested.
+
+use alloc::{format, string::String};
+use core::fmt::Write as _;
+use std::{fs, path::Path};
+
+use crate::write_if_changed;
+
+const LOAD_ORDERINGS: &[&str] = &["Relaxed", "Acquire", "SeqCst"];
+const STORE_ORDERINGS: &[&str] = &["Relaxed", "Release", "SeqCst"];
+const RMW_ORDERINGS: &[&str] = &["Relaxed", "Acquire", "Release", "AcqRel", "SeqCst"];
+const RMW_OPS: &[&str] = &[
+    "swap",
+    "fetch_add",
+    "fetch_sub",
+    "fetch_and",
+    "fetch_nand",
+    "fetch_or",
+    "fetch_xor",
+    "fetch_max",
+    "fetch_min",
+];
+
+/// Writes the crate to `dir`.
+///
+/// Files are only rewritten if their contents changed, to avoid unnecessary rebuilds.
+pub(crate) fn generate(dir: &Path, types: &[&str]) {
+    let manifest = "[package]
+name = \"asmtest-core-atomic\"
+version = \"0.0.0\"
+edition = \"2021\"
+publish = false
+
+[lib]
+path = \"lib.rs\"
+
+[workspace]
+";
+    let mut lib = String::from(
+        "// This file is @generated by asmtest.\n\n#![no_std]\n\nuse core::sync::atomic::*;\n",
+    );
+    for &ty in types {
+        let (atomic_ty, size) = match ty {
+            "i8" => ("AtomicI8", "8"),
+            "u8" => ("AtomicU8", "8"),
+            "i16" => ("AtomicI16", "16"),
+            "u16" => ("AtomicU16", "16"),
+            "i32" => ("AtomicI32", "32"),
+            "u32" => ("AtomicU32", "32"),
+            "i64" => ("AtomicI64", "64"),
+            "u64" => ("AtomicU64", "64"),
+            "isize" => ("AtomicIsize", "ptr"),
+            "usize" => ("AtomicUsize", "ptr"),
+            _ => panic!("unsupported type `{ty}` for core::sync::atomic"),
+        };
+        let cfg = format!("#[cfg(target_has_atomic = \"{size}\")]\n#[no_mangle]\n");
+        for ord in LOAD_ORDERINGS {
+            let _ = write!(
+                lib,
+                "\n{cfg}pub fn load_{ty}_{}(a: &{atomic_ty}) -> {ty} {{\n    a.load(Ordering::{ord})\n}}\n",
+                ord.to_ascii_lowercase(),
+            );
+        }
+        for ord in STORE_ORDERINGS {
+            let _ = write!(
+                lib,
+                "\n{cfg}pub fn store_{ty}_{}(a: &{atomic_ty}, val: {ty}) {{\n    a.store(val, Ordering::{ord});\n}}\n",
+                ord.to_ascii_lowercase(),
+            );
+        }
+        for op in RMW_OPS {
+            for ord in RMW_ORDERINGS {
+                let _ = write!(
+                    lib,
+                    "\n{cfg}pub fn {op}_{ty}_{}(a: &{atomic_ty}, val: {ty}) -> {ty} {{\n    a.{op}(val, Ordering::{ord})\n}}\n",
+                    ord.to_ascii_lowercase(),
+                );
+            }
+        }
+        for op in ["compare_exchange", "compare_exchange_weak"] {
+            for success in RMW_ORDERINGS {
+                for failure in LOAD_ORDERINGS {
+                    let _ = write!(
+                        lib,
+                        "\n{cfg}pub fn {op}_{ty}_{}_{}(a: &{atomic_ty}, old: {ty}, new: {ty}) -> Result<{ty}, {ty}> {{\n    a.{op}(old, new, Ordering::{success}, Ordering::{failure})\n}}\n",
+                        success.to_ascii_lowercase(),
+                        failure.to_ascii_lowercase(),
+                    );
+                }
+            }
+        }
+    }
+    fs::create_dir_all(dir).unwrap();
+    write_if_changed(&dir.join("Cargo.toml"), manifest);
+    write_if_changed(&dir.join("lib.rs"), &lib);
+}
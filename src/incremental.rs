@@ -0,0 +1,280 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Fingerprints of the inputs of each revision, used by Tester::incremental to skip
+// revisions whose inputs have not changed since the last run.
+
+use alloc::{borrow::ToOwned as _, format, string::String, vec, vec::Vec};
+use core::hash::{Hash as _, Hasher as _};
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use build_context::RUSTC;
+use cargo_config2::TargetTripleRef;
+
+use crate::{CommonConfig, DOCKER_IMAGE, Fnv1a, Revision, Tester, TesterContext, cargo};
+
+/// Computes the fingerprint of the inputs that can affect the output of the given revision.
+///
+/// This errs on the side of invalidation: it covers the whole workspace rather than only
+/// the files the crate actually uses. The hash of the workspace is cached in `sources`,
+/// keyed by [`Revision::manifest_dir`], so each workspace is read only once per dump.
+pub(crate) fn fingerprint(
+    tcx: &TesterContext<'_>,
+    revision: &Revision,
+    dump_dir: &Path,
+    sources: &mut Vec<(Option<PathBuf>, u64)>,
+) -> u64 {
+    let mut h = Fnv1a::new();
+    env!("CARGO_PKG_VERSION").hash(&mut h);
+    DOCKER_IMAGE.hash(&mut h);
+
+    // Options that affect the output.
+    hash_tester(&mut h, tcx.tester);
+    hash_revision(&mut h, revision);
+
+    // Toolchain and Cargo configuration.
+    cargo::rustc_verbose_version(revision.rustc.as_deref().unwrap_or(Path::new(RUSTC)))
+        .unwrap()
+        .hash(&mut h);
+    // Configurations for targets are resolved lazily, so hash only the one for this
+    // revision, to not depend on which revisions have been built before.
+    let target = TargetTripleRef::from(&*revision.target);
+    tcx.config.rustflags(target.clone()).unwrap();
+    // Keys of the target table are serialized in the same way as TargetTripleRef.
+    let key = serde_json::to_value(&target).unwrap();
+    let mut config = serde_json::to_value(&tcx.config).unwrap();
+    let target_config = config
+        .as_object_mut()
+        .and_then(|config| config.remove("target"))
+        .and_then(|mut targets| targets.as_object_mut()?.remove(key.as_str()?));
+    serde_json::to_string(&config).unwrap().hash(&mut h);
+    serde_json::to_string(&target_config).unwrap().hash(&mut h);
+    let mut vars: Vec<(OsString, OsString)> = env::vars_os()
+        .filter(|(k, _)| {
+            let k = k.to_string_lossy();
+            (k.starts_with("CARGO") || k.starts_with("RUST") || k.starts_with("ASMTEST_"))
+                // Contains file descriptors of the jobserver, which change on every run.
+                && k != "CARGO_MAKEFLAGS"
+        })
+        .collect();
+    vars.sort();
+    vars.hash(&mut h);
+
+    // Sources.
+    let manifest_dir = tcx.revision_manifest_dir(revision);
+    let hash = match sources.iter().find(|(dir, _)| *dir == manifest_dir) {
+        Some(&(_, hash)) => hash,
+        None => {
+            let hash = hash_sources(tcx, manifest_dir.as_deref(), dump_dir);
+            sources.push((manifest_dir, hash));
+            hash
+        }
+    };
+    hash.hash(&mut h);
+
+    h.finish()
+}
+
+fn hash_tester(h: &mut Fnv1a, tester: &Tester) {
+    // Destructure to get a compile error when a new option is added. Options that don't
+    // affect the output or the checks done on it (e.g., how differences are reported) are
+    // ignored.
+    let Tester {
+        config,
+        target_dir: _,
+        build_only: _,
+        docker_retries: _,
+        docker_user: _,
+        show_warnings: _,
+        ignore_whitespace_churn: _,
+        no_autowrite: _,
+        bless: _,
+        emit_llvm_ir,
+        only_tags: _,
+        clean: _,
+        reproducible,
+        output_naming: _,
+        on_mismatch: _,
+        host_triple,
+        function_markers,
+        visualize_jumps,
+        trim_padding,
+        strip_imm_annotations,
+        canonicalize_hex,
+        canonicalize_registers,
+        strip_cet,
+        strict_labels,
+        resolve_relocations,
+        bytes_only,
+        show_addresses,
+        head_instructions,
+        deny_empty_output,
+        emit_fingerprints: _,
+        include_file_comments,
+        diff_summary: _,
+        capture_diagnostics,
+        prebuilt,
+        objdump_overrides,
+        timeout: _,
+        incremental: _,
+        expected_tool_versions: _,
+        diff_tool: _,
+        no_color: _,
+        label_style,
+        combined_report: _,
+    } = tester;
+    hash_config(h, config);
+    (
+        emit_llvm_ir,
+        function_markers,
+        visualize_jumps,
+        trim_padding,
+        (strip_imm_annotations, canonicalize_hex, canonicalize_registers, strip_cet),
+        head_instructions,
+        objdump_overrides,
+        include_file_comments,
+        reproducible,
+        (label_style, bytes_only, capture_diagnostics, prebuilt, show_addresses),
+        host_triple,
+        (strict_labels, resolve_relocations, deny_empty_output),
+    )
+        .hash(h);
+}
+
+fn hash_config(h: &mut Fnv1a, config: &CommonConfig) {
+    // Destructure to get a compile error when a new option is added.
+    let CommonConfig {
+        cargo_args,
+        rustc_args,
+        codegen_args,
+        opt_level,
+        panic,
+        clean_rustflags,
+        objdump_args,
+        objdump_program,
+        att_syntax,
+        riscv_no_aliases,
+        riscv_numeric_reg_names,
+        show_bytes,
+        forbidden_instructions,
+        expected_functions,
+    } = config;
+    (
+        cargo_args,
+        rustc_args,
+        codegen_args,
+        opt_level,
+        panic,
+        clean_rustflags,
+        objdump_args,
+        objdump_program,
+        (att_syntax, riscv_no_aliases, riscv_numeric_reg_names, show_bytes),
+    )
+        .hash(h);
+    // Checks done on the output are also skipped with the revision, so they need to be
+    // covered as well.
+    forbidden_instructions.len().hash(h);
+    for (function_re, mnemonic) in forbidden_instructions {
+        (function_re.as_str(), mnemonic).hash(h);
+    }
+    expected_functions.len().hash(h);
+    for function_re in expected_functions {
+        function_re.as_str().hash(h);
+    }
+}
+
+fn hash_revision(h: &mut Fnv1a, revision: &Revision) {
+    // Destructure to get a compile error when a new option is added.
+    let Revision {
+        name,
+        target,
+        tags: _,
+        manifest_dir,
+        rustc,
+        target_cpu,
+        target_features,
+        disasm_features,
+        address_range,
+        only_symbol,
+        assert_soft_float,
+        config,
+    } = revision;
+    (
+        name,
+        target,
+        manifest_dir,
+        rustc,
+        target_cpu,
+        target_features,
+        disasm_features,
+        address_range,
+        only_symbol,
+        assert_soft_float,
+    )
+        .hash(h);
+    hash_config(h, config);
+}
+
+/// Hashes the sources of the workspace of the crate at `manifest_dir`, or of the tested
+/// crate if `None`.
+fn hash_sources(tcx: &TesterContext<'_>, manifest_dir: Option<&Path>, dump_dir: &Path) -> u64 {
+    let mut h = Fnv1a::new();
+    let mut skip = vec![tcx.metadata.target_directory.clone(), dump_dir.to_owned()];
+    skip.extend(tcx.metadata.build_directory.clone());
+    match manifest_dir {
+        Some(manifest_dir) => {
+            let manifest_path = cargo::locate_project(&manifest_dir.join("Cargo.toml")).unwrap();
+            let metadata = cargo::metadata(&manifest_path, None).unwrap();
+            hash_dir(&mut h, &metadata.workspace_root, &skip);
+        }
+        None => hash_dir(&mut h, &tcx.metadata.workspace_root, &skip),
+    }
+    h.finish()
+}
+
+fn hash_dir(h: &mut Fnv1a, dir: &Path, skip: &[PathBuf]) {
+    // Cargo creates CACHEDIR.TAG in target directories, including ones of other crates.
+    if dir.join("CACHEDIR.TAG").exists() {
+        return;
+    }
+    let mut entries: Vec<_> = fs::read_dir(dir).unwrap().map(Result::unwrap).collect();
+    entries.sort_by_key(fs::DirEntry::file_name);
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_name() == ".git" || skip.contains(&path) {
+            continue;
+        }
+        if entry.file_type().unwrap().is_dir() {
+            hash_dir(h, &path, skip);
+        } else {
+            path.hash(h);
+            fs::read(&path).unwrap_or_default().hash(h);
+        }
+    }
+}
+
+/// Returns `true` if the output at `output_path` was generated from inputs with the
+/// given fingerprint and has not been modified since.
+pub(crate) fn is_fresh(stamp_path: &Path, fingerprint: u64, output_path: &Path) -> bool {
+    let (Ok(stamp), Ok(output)) = (fs::read_to_string(stamp_path), fs::read(output_path)) else {
+        return false;
+    };
+    stamp == stamp_contents(fingerprint, &output)
+}
+
+/// Records that the output at `output_path` was generated from inputs with the given
+/// fingerprint.
+pub(crate) fn write_stamp(stamp_path: &Path, fingerprint: u64, output_path: &Path) {
+    let output = fs::read(output_path).unwrap();
+    fs::write(stamp_path, stamp_contents(fingerprint, &output)).unwrap();
+}
+
+fn stamp_contents(fingerprint: u64, output: &[u8]) -> String {
+    let mut h = Fnv1a::new();
+    output.hash(&mut h);
+    format!("{fingerprint:016x} {:016x}\n", h.finish())
+}
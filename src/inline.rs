@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Generates a crate that takes the addresses of the given functions of the crate being tested,
+// to force inline and generic functions to be emitted as standalone symbols.
+
+use alloc::{borrow::ToOwned as _, format, string::String};
+use core::fmt::Write as _;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::write_if_changed;
+
+/// Writes the crate to `dir`.
+///
+/// `functions` are paths relative to the root of the crate at `dep_dir`, whose package
+/// name is `dep_package`. Functions that are neither `#[inline]` nor generic are not emitted
+/// in the generated crate, because taking their addresses refers to the symbols emitted in the
+/// crate that defines them.
+pub(crate) fn generate(dir: &Path, dep_package: &str, dep_dir: &Path, functions: &[&str]) {
+    fs::create_dir_all(dir).unwrap();
+    // The dependency is referred to by a relative path, which can be written in the manifest
+    // even if the absolute path is not valid UTF-8 (e.g., if the target directory is inside
+    // the crate being tested).
+    let dep_path = match relative_path(dir, dep_dir).to_str() {
+        Some(path) => path.to_owned(),
+        None => dep_symlink(dir, dep_package, dep_dir),
+    };
+    let manifest = format!(
+        "[package]
+name = \"asmtest-inline\"
+version = \"0.0.0\"
+edition = \"2021\"
+publish = false
+
+[lib]
+path = \"lib.rs\"
+
+[dependencies]
+krate = {{ package = {}, path = {} }}
+
+[workspace]
+",
+        toml_string(dep_package),
+        toml_string(&dep_path),
+    );
+    // Taking the address of a function forces the instance to be emitted in this crate
+    // even if it is #[inline] or generic, under its own (demangled) path, unlike
+    // #[inline(never)] #[no_mangle] wrappers that require the signature to be written.
+    let mut lib = String::from(
+        "// This file is @generated by asmtest.
+
+#![no_std]
+
+#[allow(dead_code)]
+struct Keep(*const ());
+// SAFETY: the pointers are never dereferenced.
+unsafe impl Sync for Keep {}
+
+#[used]
+static KEEP: &[Keep] = &[\n",
+    );
+    for path in functions {
+        let _ = writeln!(lib, "    Keep(krate::{path} as *const ()),");
+    }
+    lib.push_str("];\n");
+    write_if_changed(&dir.join("Cargo.toml"), &manifest);
+    write_if_changed(&dir.join("lib.rs"), &lib);
+}
+
+/// Returns the path to `to` relative to `from`, or `to` as is if they don't share the root
+/// (e.g., on different drives on Windows).
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let mut from = from.components().peekable();
+    let mut to = to.components().peekable();
+    let mut common = 0;
+    while let (Some(a), Some(b)) = (from.peek(), to.peek()) {
+        if a != b {
+            break;
+        }
+        from.next();
+        to.next();
+        common += 1;
+    }
+    if common == 0 {
+        return to.collect();
+    }
+    let mut path: PathBuf = from.map(|_| Component::ParentDir).collect();
+    path.extend(to);
+    path
+}
+
+/// Creates a symlink to `dep_dir` next to `dir` and returns the path to it relative to `dir`,
+/// for paths that cannot be written in the manifest.
+///
+/// The symlink is outside of `dir` so that the dependency doesn't become a member of the
+/// workspace of the generated crate.
+#[cfg(unix)]
+fn dep_symlink(dir: &Path, dep_package: &str, dep_dir: &Path) -> String {
+    let name = format!("{dep_package}.src");
+    let link = dir.parent().unwrap().join(&name);
+    if fs::read_link(&link).ok().as_deref() != Some(dep_dir) {
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink(dep_dir, &link).unwrap();
+    }
+    format!("../{name}")
+}
+#[cfg(not(unix))]
+fn dep_symlink(_dir: &Path, _dep_package: &str, dep_dir: &Path) -> String {
+    panic!("path to the crate is not valid Unicode: {}", dep_dir.display())
+}
+
+/// Returns `s` as a TOML basic string.
+fn toml_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04X}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("/a/b/target/tests/asmtest/inline/b"), Path::new("/a/b")),
+            Path::new("../../../../..")
+        );
+        assert_eq!(
+            relative_path(Path::new("/t/inline/b"), Path::new("/a/b")),
+            Path::new("../../../a/b")
+        );
+    }
+
+    #[test]
+    fn test_toml_string() {
+        assert_eq!(toml_string("foo"), "\"foo\"");
+        assert_eq!(toml_string("C:\\a \"b\"\n\u{7f}"), "\"C:\\\\a \\\"b\\\"\\n\\u007F\"");
+    }
+}
@@ -7,7 +7,7 @@
 
 A library for tracking generated assemblies.
 
-See [atomic-maybe-uninit#55](https://github.com/taiki-e/atomic-maybe-uninit/pull/55) for an usage example.
+See [tests/dump.rs](https://github.com/taiki-e/asmtest/blob/HEAD/tests/dump.rs) for a minimal usage example, and [atomic-maybe-uninit#55](https://github.com/taiki-e/atomic-maybe-uninit/pull/55) for a real-world usage example.
 
 ## Exclude generated assemblies from GitHub's language stats
 
@@ -60,13 +60,19 @@ extern crate std;
 mod process;
 
 mod cargo;
+mod core_atomic;
+mod incremental;
+mod inline;
+mod llvm_ir;
 mod objdump;
 
-#[cfg(windows)]
-use alloc::borrow::ToOwned as _;
-#[cfg(not(windows))]
-use alloc::format;
-use alloc::{string::String, vec, vec::Vec};
+use alloc::{boxed::Box, format, string::String, vec, vec::Vec};
+use core::{
+    cell::{Cell, RefCell},
+    fmt,
+    hash::Hasher,
+    time::Duration,
+};
 use std::{
     env, eprintln,
     ffi::OsString,
@@ -74,12 +80,15 @@ use std::{
     io::{self, IsTerminal as _, Write as _},
     path::{Path, PathBuf},
     process::Stdio,
+    thread,
+    time::Instant,
 };
 
 use cargo_config2::{
     TargetTripleRef,
     cfg::{TargetArch, TargetEndian},
 };
+use regex::Regex;
 
 use self::process::ProcessBuilder;
 
@@ -87,8 +96,18 @@ use self::process::ProcessBuilder;
 struct CommonConfig {
     cargo_args: Vec<String>,
     rustc_args: Vec<String>,
+    codegen_args: Vec<String>,
+    opt_level: Option<String>,
+    panic: Option<Panic>,
+    clean_rustflags: bool,
     objdump_args: Vec<String>,
+    objdump_program: Option<String>,
     att_syntax: bool,
+    riscv_no_aliases: bool,
+    riscv_numeric_reg_names: bool,
+    show_bytes: bool,
+    forbidden_instructions: Vec<(Regex, String)>,
+    expected_functions: Vec<Regex>,
 }
 
 #[derive(Debug)]
@@ -96,12 +115,201 @@ struct CommonConfig {
 pub struct Revision {
     name: String,
     target: String,
+    tags: Vec<String>,
+    manifest_dir: Option<PathBuf>,
+    rustc: Option<PathBuf>,
+    target_cpu: Option<String>,
+    target_features: Vec<String>,
+    disasm_features: Vec<String>,
+    address_range: Option<(u64, u64)>,
+    only_symbol: Option<String>,
+    assert_soft_float: bool,
     config: CommonConfig,
 }
 
 impl Revision {
+    /// Creates a new revision for the given target triple.
+    ///
+    /// `name` is used as the file name of the generated assembly, so it must be
+    /// unique among the revisions passed to [`Tester::dump`].
     pub fn new<N: Into<String>, T: Into<String>>(name: N, target: T) -> Self {
-        Self { name: name.into(), target: target.into(), config: CommonConfig::default() }
+        Self {
+            name: name.into(),
+            target: target.into(),
+            tags: vec![],
+            manifest_dir: None,
+            rustc: None,
+            target_cpu: None,
+            target_features: vec![],
+            disasm_features: vec![],
+            address_range: None,
+            only_symbol: None,
+            assert_soft_float: false,
+            config: CommonConfig::default(),
+        }
+    }
+    /// Creates revisions for the little-endian and big-endian variants of an architecture,
+    /// named `<name>_le` and `<name>_be`.
+    ///
+    /// Options can be applied to both revisions with [`array::map`]
+    /// (e.g., `Revision::both_endian(..).map(|r| r.tag("mips"))`).
+    ///
+    /// [`array::map`]: https://doc.rust-lang.org/std/primitive.array.html#method.map
+    pub fn both_endian<N: AsRef<str>, L: Into<String>, B: Into<String>>(
+        name: N,
+        le_target: L,
+        be_target: B,
+    ) -> [Self; 2] {
+        let name = name.as_ref();
+        [Self::new(format!("{name}_le"), le_target), Self::new(format!("{name}_be"), be_target)]
+    }
+
+    /// Returns the name of this revision.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Returns the target triple of this revision.
+    #[must_use]
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Adds a tag to this revision.
+    ///
+    /// Tags are used to select revisions to run via [`Tester::only_tags`] or
+    /// the `ASMTEST_TAGS` environment variable.
+    pub fn tag<T: Into<String>>(mut self, tag: T) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Builds the crate at the given directory instead of the one passed to [`Tester::dump`]
+    /// (and other methods of [`Tester`]) for this revision.
+    ///
+    /// This is useful to compare the assemblies generated from different crates
+    /// (e.g., your implementation and a reference implementation) in a single test.
+    ///
+    /// `path` is resolved relative to the directory of the crate passed to [`Tester`].
+    pub fn manifest_dir<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.manifest_dir = Some(path.into());
+        self
+    }
+
+    /// Sets the path to the compiler to build this revision.
+    ///
+    /// This is useful to compare the assemblies generated by different compilers
+    /// (e.g., stable and nightly) in a single test.
+    pub fn rustc<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.rustc = Some(path.into());
+        self
+    }
+
+    /// Only disassembles instructions in the given address range (`--start-address` and
+    /// `--stop-address` of objdump), where `stop` is exclusive.
+    ///
+    /// This is useful to focus on a specific part of a large object file. Note that in
+    /// object files, each function is usually placed in its own section that starts at
+    /// address zero, and the range is applied to each section.
+    ///
+    /// `start` must not be in the middle of a function, otherwise dumping this revision
+    /// panics. `stop` may be in the middle of a function; the rest of the function is omitted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` is greater than `stop`.
+    pub fn address_range(mut self, start: u64, stop: u64) -> Self {
+        assert!(start <= stop, "start address {start:#x} is greater than stop address {stop:#x}");
+        self.address_range = Some((start, stop));
+        self
+    }
+    /// Disassembles only the given symbol, instead of disassembling the whole object and
+    /// filtering functions.
+    ///
+    /// This is useful to dump a single function of a large crate faster. `name` can be the
+    /// mangled name or the demangled name with or without the hash (e.g., `foo::bar`). All
+    /// symbols matching `name` are disassembled, sorted by name.
+    ///
+    /// # Panics
+    ///
+    /// Dumping this revision panics if no symbols match `name`, or if they cannot be selected by
+    /// the objdump in use (GNU objdump selects only one symbol, and llvm-objdump cannot select
+    /// symbols whose demangled names contain commas).
+    pub fn only_symbol<N: Into<String>>(mut self, name: N) -> Self {
+        self.only_symbol = Some(name.into());
+        self
+    }
+    /// Asserts that the target of this revision uses a soft-float ABI.
+    ///
+    /// The float ABI is determined by the target (e.g., `thumbv7em-none-eabi` vs
+    /// `thumbv7em-none-eabihf`), and rustc no longer accepts `-C soft-float` and warns about
+    /// target features that change the float ABI, so there is no flag that reliably turns
+    /// hard-float code into soft-float code. This catches revisions that are intended to test
+    /// soft-float code but silently use a hard-float target.
+    ///
+    /// This is supported on 32-bit Arm, AArch64, C-SKY, LoongArch, RISC-V, and x86. On other
+    /// architectures, a warning is printed and the check is skipped.
+    ///
+    /// # Panics
+    ///
+    /// Dumping this revision panics if the target uses a hard-float ABI.
+    pub fn assert_soft_float(mut self) -> Self {
+        self.assert_soft_float = true;
+        self
+    }
+
+    /// Sets the CPU to generate code for (`-C target-cpu`).
+    ///
+    /// Default is the baseline CPU of the target.
+    pub fn target_cpu<C: Into<String>>(mut self, cpu: C) -> Self {
+        self.target_cpu = Some(cpu.into());
+        self
+    }
+
+    /// Enables or disables target features (`-C target-feature`).
+    ///
+    /// Each feature must be prefixed with `+` to enable it or `-` to disable it
+    /// (e.g., `["+lse", "-neon"]`).
+    ///
+    /// Default is the baseline features of the target.
+    pub fn target_feature<I: IntoIterator<Item = S>, S: Into<String>>(
+        mut self,
+        features: I,
+    ) -> Self {
+        for feature in features {
+            let feature = feature.into();
+            assert!(
+                feature.starts_with(['+', '-']),
+                "target feature must be prefixed with `+` or `-`: `{feature}`"
+            );
+            self.target_features.push(feature);
+        }
+        self
+    }
+    /// Enables or disables target features of the disassembler (`--mattr` of llvm-objdump).
+    ///
+    /// Each feature must be prefixed with `+` to enable it or `-` to disable it
+    /// (e.g., `["+zacas", "+zabha"]`).
+    ///
+    /// This is needed when instructions of extensions that are not enabled by default
+    /// for the target are shown as `<unknown>`. Unlike [`Revision::target_feature`],
+    /// this doesn't affect code generation.
+    ///
+    /// GNU objdump decodes extensions based on the object file, so this is ignored with
+    /// a warning if GNU objdump is used.
+    pub fn disasm_features<I: IntoIterator<Item = S>, S: Into<String>>(
+        mut self,
+        features: I,
+    ) -> Self {
+        for feature in features {
+            let feature = feature.into();
+            assert!(
+                feature.starts_with(['+', '-']),
+                "disassembler feature must be prefixed with `+` or `-`: `{feature}`"
+            );
+            self.disasm_features.push(feature);
+        }
+        self
     }
 
     /// Adds additional command line arguments for `cargo`. (this revision only)
@@ -120,6 +328,49 @@ impl Revision {
         self.config.rustc_args.extend(args.into_iter().map(Into::into));
         self
     }
+    /// Adds additional codegen options (`-C`) or unstable options (`-Z`) to pass to rustc. (this revision only)
+    ///
+    /// Each argument must be in the form of `-C key`, `-C key=value`, or their forms
+    /// without the space (e.g., `-Clink-dead-code`, `-Z function-sections=yes`).
+    ///
+    /// This will be merged with options passed via [`Tester::codegen_args`].
+    ///
+    /// `-C codegen-units` is ignored; see [`Tester::codegen_args`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if an argument is not well-formed.
+    pub fn codegen_args<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, args: I) -> Self {
+        self.config
+            .codegen_args
+            .extend(args.into_iter().flat_map(|a| parse_codegen_arg(a.as_ref())));
+        self
+    }
+    /// Sets the optimization level (`0`, `1`, `2`, `3`, `s`, or `z`). (this revision only)
+    ///
+    /// This takes precedence over the level passed via [`Tester::opt_level`] and
+    /// the level set in the release profile.
+    pub fn opt_level<L: Into<String>>(mut self, level: L) -> Self {
+        self.config.opt_level = Some(level.into());
+        self
+    }
+    /// Sets the panic strategy. (this revision only)
+    ///
+    /// This takes precedence over the strategy passed via [`Tester::panic`].
+    /// Default is the default of the target.
+    pub fn panic(mut self, strategy: Panic) -> Self {
+        self.config.panic = Some(strategy);
+        self
+    }
+    /// Ignores rustflags from the environment (`RUSTFLAGS`, etc.) and the Cargo
+    /// configuration (`build.rustflags`, etc.). (this revision only)
+    ///
+    /// Only the flags passed via asmtest's options are used, so the generated assembly
+    /// doesn't depend on the local configuration of the developer.
+    pub fn clean_rustflags(mut self) -> Self {
+        self.config.clean_rustflags = true;
+        self
+    }
     /// Adds additional command line arguments for objdump. (this revision only)
     ///
     /// This will be merged with the arguments passed via [`Tester::objdump_args`].
@@ -127,6 +378,16 @@ impl Revision {
         self.config.objdump_args.extend(args.into_iter().map(Into::into));
         self
     }
+    /// Uses the given program instead of `objdump` or `llvm-objdump` for disassembling. (this revision only)
+    ///
+    /// This takes precedence over the program passed via [`Tester::objdump_program`].
+    ///
+    /// The program is run via `docker run` like the default ones, so it must be
+    /// available in the container and accept the same options as the program it replaces.
+    pub fn objdump_program<P: Into<String>>(mut self, program: P) -> Self {
+        self.config.objdump_program = Some(program.into());
+        self
+    }
     /// Use AT&T syntax in x86/x86_64 assemblies. (this revision only)
     ///
     /// By default, Intel syntax is used to match Rust inline assembly.
@@ -134,18 +395,208 @@ impl Revision {
         self.config.att_syntax = true;
         self
     }
+    /// Disables pseudo-instructions (e.g., `mv`, `li`, `ret`) in RISC-V assemblies. (this revision only)
+    ///
+    /// By default, pseudo-instructions are used.
+    pub fn riscv_no_aliases(mut self) -> Self {
+        self.config.riscv_no_aliases = true;
+        self
+    }
+    /// Uses numeric register names (e.g., `x10`) instead of ABI names (e.g., `a0`)
+    /// in RISC-V assemblies. (this revision only)
+    ///
+    /// By default, ABI names are used.
+    pub fn riscv_numeric_reg_names(mut self) -> Self {
+        self.config.riscv_numeric_reg_names = true;
+        self
+    }
+    /// Shows raw instruction bytes alongside mnemonics. (this revision only)
+    ///
+    /// The bytes are emitted in a trailing column like `mov eax, esi ; 89 f0`.
+    pub fn show_bytes(mut self) -> Self {
+        self.config.show_bytes = true;
+        self
+    }
+    /// Asserts that functions whose names match `function_regex` do not contain
+    /// the given instruction. (this revision only)
+    ///
+    /// This will be merged with the instructions passed via [`Tester::forbid_instruction`].
+    ///
+    /// `function_regex` is matched against the function name as it appears in the
+    /// generated assembly. This can be called multiple times to forbid multiple instructions.
+    pub fn forbid_instruction<M: Into<String>>(
+        mut self,
+        function_regex: &str,
+        mnemonic: M,
+    ) -> Self {
+        self.config
+            .forbidden_instructions
+            .push((Regex::new(function_regex).unwrap(), mnemonic.into()));
+        self
+    }
+    /// Asserts that, for each of the given regexes, at least one function whose
+    /// name matches it is emitted. (this revision only)
+    ///
+    /// This will be merged with the regexes passed via [`Tester::expect_functions`].
+    ///
+    /// This catches functions that were unexpectedly merged or not emitted.
+    pub fn expect_functions<I: IntoIterator<Item = S>, S: AsRef<str>>(
+        mut self,
+        function_regexes: I,
+    ) -> Self {
+        self.config
+            .expected_functions
+            .extend(function_regexes.into_iter().map(|re| Regex::new(re.as_ref()).unwrap()));
+        self
+    }
+}
+
+/// The function set by [`Tester::on_mismatch`].
+struct OnMismatch(Box<dyn Fn(&str, &Path, &[u8]) + Send + Sync>);
+
+impl fmt::Debug for OnMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnMismatch(..)")
+    }
 }
 
 #[derive(Debug)]
 #[must_use]
 pub struct Tester {
     config: CommonConfig,
+    target_dir: Option<PathBuf>,
+    build_only: bool,
+    docker_retries: u32,
+    docker_user: Option<String>,
+    show_warnings: bool,
+    ignore_whitespace_churn: bool,
+    no_autowrite: bool,
+    bless: bool,
+    emit_llvm_ir: bool,
+    only_tags: Vec<String>,
+    clean: bool,
+    reproducible: bool,
+    output_naming: Option<fn(&Revision) -> PathBuf>,
+    on_mismatch: Option<OnMismatch>,
+    host_triple: Option<String>,
+    function_markers: bool,
+    visualize_jumps: bool,
+    trim_padding: bool,
+    strip_imm_annotations: bool,
+    canonicalize_hex: bool,
+    canonicalize_registers: bool,
+    strip_cet: bool,
+    strict_labels: bool,
+    resolve_relocations: bool,
+    bytes_only: bool,
+    show_addresses: bool,
+    head_instructions: Option<usize>,
+    deny_empty_output: bool,
+    emit_fingerprints: bool,
+    include_file_comments: bool,
+    diff_summary: bool,
+    capture_diagnostics: bool,
+    prebuilt: bool,
+    /// Pairs of `target_arch` and whether to use GNU binutils for it.
+    objdump_overrides: Vec<(String, bool)>,
+    timeout: Option<Duration>,
+    incremental: bool,
+    expected_tool_versions: Vec<(String, String)>,
+    diff_tool: DiffTool,
+    no_color: bool,
+    label_style: LabelStyle,
+    combined_report: Option<Regex>,
 }
 
 impl Tester {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Self { config: CommonConfig::default() }
+        Self {
+            config: CommonConfig::default(),
+            target_dir: None,
+            build_only: false,
+            docker_retries: 2,
+            docker_user: None,
+            show_warnings: false,
+            ignore_whitespace_churn: false,
+            no_autowrite: false,
+            bless: false,
+            emit_llvm_ir: false,
+            only_tags: vec![],
+            clean: false,
+            reproducible: false,
+            output_naming: None,
+            on_mismatch: None,
+            host_triple: None,
+            function_markers: false,
+            visualize_jumps: false,
+            trim_padding: false,
+            strip_imm_annotations: false,
+            canonicalize_hex: false,
+            canonicalize_registers: false,
+            strip_cet: false,
+            strict_labels: false,
+            resolve_relocations: false,
+            bytes_only: false,
+            show_addresses: false,
+            head_instructions: None,
+            deny_empty_output: false,
+            emit_fingerprints: false,
+            include_file_comments: false,
+            diff_summary: false,
+            capture_diagnostics: false,
+            prebuilt: false,
+            objdump_overrides: vec![],
+            timeout: None,
+            incremental: false,
+            expected_tool_versions: vec![],
+            diff_tool: DiffTool::Git,
+            no_color: false,
+            label_style: LabelStyle::Numeric,
+            combined_report: None,
+        }
+    }
+
+    /// Returns revisions for representative targets of all architectures confirmed to work
+    /// (one or a few per architecture), named by their target triples.
+    ///
+    /// Many of them are tier 3 targets, which require `-Z build-std` (see
+    /// [`Tester::cargo_args`]) and the nightly toolchain.
+    #[must_use]
+    pub fn targets() -> Vec<Revision> {
+        const TARGETS: &[&str] = &[
+            "x86_64-unknown-linux-gnu",
+            "i686-unknown-linux-gnu",
+            "aarch64-unknown-linux-gnu",
+            "aarch64_be-unknown-linux-gnu",
+            "arm64ec-pc-windows-msvc",
+            "armv7-unknown-linux-gnueabihf",
+            "thumbv6m-none-eabi",
+            "thumbv7em-none-eabihf",
+            "riscv32imac-unknown-none-elf",
+            "riscv64gc-unknown-linux-gnu",
+            "loongarch64-unknown-linux-gnu",
+            "s390x-unknown-linux-gnu",
+            "mips-unknown-linux-gnu",
+            "mipsel-unknown-linux-gnu",
+            "mips64-unknown-linux-gnuabi64",
+            "mipsisa32r6-unknown-linux-gnu",
+            "powerpc-unknown-linux-gnu",
+            "powerpc64-unknown-linux-gnu",
+            "powerpc64le-unknown-linux-gnu",
+            "sparc-unknown-linux-gnu",
+            "sparc64-unknown-linux-gnu",
+            "msp430-none-elf",
+            "hexagon-unknown-linux-musl",
+            "m68k-unknown-linux-gnu",
+            "csky-unknown-linux-gnuabiv2",
+            "xtensa-esp32-none-elf",
+            "wasm32-unknown-unknown",
+        ];
+        let mut revisions: Vec<_> = TARGETS.iter().map(|&t| Revision::new(t, t)).collect();
+        // avr-none requires the CPU to be specified.
+        revisions.push(Revision::new("avr-none", "avr-none").target_cpu("atmega328p"));
+        revisions
     }
 
     /// Dump assemblies for the given revisions.
@@ -157,7 +608,344 @@ impl Tester {
         dump_dir: D,
         revisions: &[Revision],
     ) {
-        dump(self, manifest_dir.as_ref(), dump_dir.as_ref(), revisions);
+        let manifest_dir = manifest_dir.as_ref();
+        let tcx = &TesterContext::new(
+            self,
+            manifest_dir,
+            self.resolve_target_dir(manifest_dir),
+            Docker::detect(),
+        );
+        tcx.check_tool_versions();
+        dump(tcx, dump_dir.as_ref(), revisions);
+    }
+
+    /// Dump assemblies of multiple crates for the given revisions.
+    ///
+    /// This is equivalent to calling [`Tester::dump`] for each pair of `manifest_dir`
+    /// and `dump_dir`, but detection of the container engine is done only once and
+    /// the cargo metadata/config is loaded only once per distinct `manifest_dir`.
+    ///
+    /// Each `dump_dir` is resolved to `manifest_dir.join(dump_dir)`.
+    pub fn dump_many<M: AsRef<Path>, D: AsRef<Path>>(
+        &self,
+        crates: &[(M, D)],
+        revisions: &[Revision],
+    ) {
+        let docker = Docker::detect();
+        let mut contexts: Vec<(&Path, TesterContext<'_>)> = vec![];
+        for (manifest_dir, dump_dir) in crates {
+            let manifest_dir = manifest_dir.as_ref();
+            let i = match contexts.iter().position(|(m, _)| *m == manifest_dir) {
+                Some(i) => i,
+                None => {
+                    let tcx = TesterContext::new(
+                        self,
+                        manifest_dir,
+                        self.resolve_target_dir(manifest_dir),
+                        docker.clone(),
+                    );
+                    if contexts.is_empty() {
+                        tcx.check_tool_versions();
+                    }
+                    contexts.push((manifest_dir, tcx));
+                    contexts.len() - 1
+                }
+            };
+            dump(&contexts[i].1, dump_dir.as_ref(), revisions);
+        }
+    }
+
+    /// Dump assembly for the given revision, and returns it as a string
+    /// instead of comparing it with the file.
+    ///
+    /// This is useful if you want to run custom checks on the generated assembly.
+    ///
+    /// Returns an empty string if [`Tester::build_only`] is set.
+    #[must_use]
+    pub fn dump_to_string<M: AsRef<Path>>(&self, manifest_dir: M, revision: &Revision) -> String {
+        let manifest_dir = manifest_dir.as_ref();
+        let tcx = &TesterContext::new(
+            self,
+            manifest_dir,
+            self.resolve_target_dir(manifest_dir),
+            Docker::detect(),
+        );
+        tcx.check_tool_versions();
+        dump_revision(tcx, revision, None, None, None).unwrap_or_default()
+    }
+
+    /// Dump assembly for the given revision, and returns the parsed instructions of each function.
+    ///
+    /// This is useful if you want to analyze the generated assembly programmatically
+    /// (e.g., counting memory accesses, or checking the order of fences).
+    /// Branch targets in the operands are already replaced with local labels as in the
+    /// generated files.
+    ///
+    /// Returns an empty vector if [`Tester::build_only`] or [`Tester::emit_llvm_ir`] is set.
+    #[must_use]
+    pub fn parse<M: AsRef<Path>>(
+        &self,
+        manifest_dir: M,
+        revision: &Revision,
+    ) -> Vec<(String, Vec<Asm>)> {
+        let manifest_dir = manifest_dir.as_ref();
+        let tcx = &TesterContext::new(
+            self,
+            manifest_dir,
+            self.resolve_target_dir(manifest_dir),
+            Docker::detect(),
+        );
+        tcx.check_tool_versions();
+        let mut parsed = vec![];
+        dump_revision(tcx, revision, None, Some(&mut parsed), None);
+        parsed
+    }
+
+    /// Dump assembly for the given revision, and asserts that the instructions of the
+    /// function named `function_name` are equal to `expected`.
+    ///
+    /// This is useful for small tests of specific functions without managing files.
+    ///
+    /// `expected` is compared with the instructions (without the line of the function name)
+    /// as in the generated files, but leading and trailing spaces of each line, empty lines,
+    /// and the amount of spaces (outside of string literals) are ignored. For example:
+    ///
+    /// ```text
+    /// "
+    ///     ldr w0, [x0]
+    ///     ret
+    /// "
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function is not found, if the instructions differ (after showing the
+    /// diff), or if [`Tester::build_only`] or [`Tester::emit_llvm_ir`] is set.
+    pub fn assert_function<M: AsRef<Path>>(
+        &self,
+        manifest_dir: M,
+        revision: &Revision,
+        function_name: &str,
+        expected: &str,
+    ) {
+        assert!(
+            !self.build_only && !self.emit_llvm_ir,
+            "Tester::assert_function cannot be used with Tester::build_only or Tester::emit_llvm_ir"
+        );
+        let manifest_dir = manifest_dir.as_ref();
+        let tcx = &TesterContext::new(
+            self,
+            manifest_dir,
+            self.resolve_target_dir(manifest_dir),
+            Docker::detect(),
+        );
+        tcx.check_tool_versions();
+        let out = dump_revision(tcx, revision, None, None, None).unwrap();
+        let mut names = vec![];
+        let mut actual = None;
+        for func in out.split("\n\n") {
+            // Skip the comment emitted by Tester::function_markers.
+            let mut lines = func.lines().skip_while(|line| line.starts_with(';'));
+            let Some(name) = lines.next().and_then(|line| line.strip_suffix(':')) else {
+                continue;
+            };
+            if name == function_name {
+                let mut body = String::new();
+                for line in lines {
+                    body.push_str(line);
+                    body.push('\n');
+                }
+                actual = Some(body);
+                break;
+            }
+            names.push(name);
+        }
+        let Some(actual) = actual else {
+            panic!(
+                "function `{function_name}` not found (revision {}); found functions: {names:?}",
+                revision.name
+            );
+        };
+        let expected = normalize_function_body(expected);
+        let actual = normalize_function_body(&actual);
+        if expected != actual {
+            let dir = tcx.metadata.target_directory.join("tests/asmtest/function");
+            fs::create_dir_all(&dir).unwrap();
+            let expected_path = dir.join(revision.name.clone() + ".asm");
+            fs::write(&expected_path, expected).unwrap();
+            show_diff(tcx, &dir, &expected_path, actual.as_bytes());
+            panic!(
+                "assertion failed; instructions of function `{function_name}` (revision {}) differ from the expected ones",
+                revision.name
+            );
+        }
+    }
+
+    /// Dump assemblies for the given revisions, and asserts that all of them are identical.
+    ///
+    /// This is useful for revisions that are expected to generate the same assembly
+    /// (e.g., endian pairs or ABI variants), instead of maintaining identical files.
+    ///
+    /// Does nothing except building if [`Tester::build_only`] is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the normalized assembly of any revision differs from the one of the first revision.
+    pub fn assert_equal<M: AsRef<Path>>(&self, manifest_dir: M, revisions: &[Revision]) {
+        let manifest_dir = manifest_dir.as_ref();
+        let tcx = &TesterContext::new(
+            self,
+            manifest_dir,
+            self.resolve_target_dir(manifest_dir),
+            Docker::detect(),
+        );
+        tcx.check_tool_versions();
+        let Some((first, rest)) = revisions.split_first() else { return };
+        eprintln!("testing revision {}", first.name);
+        let Some(first_out) = dump_revision(tcx, first, None, None, None) else {
+            for revision in rest {
+                eprintln!("testing revision {}", revision.name);
+                dump_revision(tcx, revision, None, None, None);
+            }
+            return;
+        };
+        let dir = tcx.metadata.target_directory.join("tests/asmtest/equal");
+        for revision in rest {
+            eprintln!("testing revision {}", revision.name);
+            let out = dump_revision(tcx, revision, None, None, None).unwrap();
+            if out != first_out {
+                fs::create_dir_all(&dir).unwrap();
+                let first_path = dir.join(first.name.clone() + ".asm");
+                fs::write(&first_path, &first_out).unwrap();
+                show_diff(tcx, &dir, &first_path, out.as_bytes());
+                panic!(
+                    "assertion failed; assembly of revision `{}` differs from revision `{}`",
+                    revision.name, first.name
+                );
+            }
+        }
+    }
+
+    /// Dump assemblies of `core::sync::atomic` operations on the given types for the given revisions.
+    ///
+    /// This generates a crate that exposes `load`, `store`, `swap`, `fetch_*`, and
+    /// `compare_exchange{,_weak}` for each type and ordering as `#[no_mangle]` functions
+    /// (e.g., `load_u32_acquire`, `compare_exchange_u32_acqrel_relaxed`), builds it in the
+    /// target directory of the crate at `manifest_dir`, and dumps its assemblies.
+    /// This is useful to compare your implementation with the one generated by the compiler.
+    ///
+    /// `types` are integer types such as `"u8"`, `"i64"`, or `"usize"`. Functions for
+    /// types whose atomic operations are not available on the target are not emitted.
+    ///
+    /// `dump_dir` is resolved to `manifest_dir.join(dump_dir)`.
+    pub fn dump_core_atomic<M: AsRef<Path>, D: AsRef<Path>>(
+        &self,
+        manifest_dir: M,
+        dump_dir: D,
+        types: &[&str],
+        revisions: &[Revision],
+    ) {
+        let manifest_dir = manifest_dir.as_ref();
+        let manifest_path = cargo::locate_project(&manifest_dir.join("Cargo.toml")).unwrap();
+        let target_dir = self.resolve_target_dir(manifest_dir);
+        let metadata = cargo::metadata(&manifest_path, target_dir.as_deref()).unwrap();
+        let manifest_dir = Path::new(&manifest_path).parent().unwrap();
+        let dump_dir = manifest_dir.join(dump_dir);
+        let raw_dump_dir = metadata
+            .target_directory
+            .join("tests/asmtest/raw")
+            .join(dump_dir.strip_prefix(manifest_dir).unwrap());
+        let crate_dir = metadata.target_directory.join("tests/asmtest/core-atomic");
+        core_atomic::generate(&crate_dir, types);
+        let tcx = &TesterContext::new(
+            self,
+            &crate_dir,
+            Some(metadata.target_directory),
+            Docker::detect(),
+        );
+        tcx.check_tool_versions();
+        dump_revisions(tcx, &dump_dir, &raw_dump_dir, revisions);
+    }
+
+    /// Dump assemblies of the given functions of the crate at `manifest_dir` for the given revisions,
+    /// including ones that are `#[inline]` or generic.
+    ///
+    /// Such functions are usually not emitted as standalone symbols in the object file of the
+    /// crate that defines them, so [`Tester::dump`] doesn't show them. This generates a crate
+    /// that depends on the crate at `manifest_dir` and forces the given functions to be emitted
+    /// in it, builds it in the target directory of the crate at `manifest_dir`, and dumps its
+    /// assemblies. Functions are shown with their paths including generic arguments
+    /// (e.g., `my_crate::max::<u64>`).
+    ///
+    /// `functions` are paths relative to the crate root, with generic arguments for generic
+    /// functions, such as `"add"`, `"ops::max::<u64>"`, or `"Wrapper::<u8>::get"`.
+    /// Functions that are neither `#[inline]` nor generic are emitted in the crate that
+    /// defines them and are not shown; use [`Tester::dump`] for them.
+    ///
+    /// `dump_dir` is resolved to `manifest_dir.join(dump_dir)`.
+    pub fn dump_inline_functions<M: AsRef<Path>, D: AsRef<Path>>(
+        &self,
+        manifest_dir: M,
+        dump_dir: D,
+        functions: &[&str],
+        revisions: &[Revision],
+    ) {
+        let manifest_dir = manifest_dir.as_ref();
+        let manifest_path = cargo::locate_project(&manifest_dir.join("Cargo.toml")).unwrap();
+        let target_dir = self.resolve_target_dir(manifest_dir);
+        let metadata = cargo::metadata(&manifest_path, target_dir.as_deref()).unwrap();
+        let manifest_dir = Path::new(&manifest_path).parent().unwrap();
+        let dump_dir = manifest_dir.join(dump_dir);
+        let raw_dump_dir = metadata
+            .target_directory
+            .join("tests/asmtest/raw")
+            .join(dump_dir.strip_prefix(manifest_dir).unwrap());
+        let package = metadata
+            .packages
+            .iter()
+            .find(|p| p.manifest_path == manifest_path)
+            .unwrap_or_else(|| panic!("not found package for {manifest_path}"));
+        let crate_dir = metadata.target_directory.join("tests/asmtest/inline").join(&package.name);
+        inline::generate(&crate_dir, &package.name, manifest_dir, functions);
+        let mut tcx =
+            TesterContext::new(self, &crate_dir, Some(metadata.target_directory), Docker::detect());
+        tcx.check_tool_versions();
+        // The legacy mangling scheme doesn't include generic arguments in symbol names.
+        tcx.rustc_args = &["-C", "symbol-mangling-version=v0"];
+        dump_revisions(&tcx, &dump_dir, &raw_dump_dir, revisions);
+    }
+
+    /// Dump assemblies of the given instantiations of generic functions of the crate at
+    /// `manifest_dir` for the given revisions.
+    ///
+    /// `instantiations` are pairs of the path of a generic function relative to the crate root
+    /// and the lists of its generic arguments, such as `("max", &["u32", "u64"])` or
+    /// `("convert", &["u8, u32"])`. Each instantiation is shown with its generic arguments
+    /// (e.g., `my_crate::max::<u32>`).
+    ///
+    /// This is a shorthand for [`Tester::dump_inline_functions`] with paths such as
+    /// `"max::<u32>"`.
+    ///
+    /// `dump_dir` is resolved to `manifest_dir.join(dump_dir)`.
+    pub fn dump_instantiations<M: AsRef<Path>, D: AsRef<Path>>(
+        &self,
+        manifest_dir: M,
+        dump_dir: D,
+        instantiations: &[(&str, &[&str])],
+        revisions: &[Revision],
+    ) {
+        let mut paths = vec![];
+        for &(path, args_list) in instantiations {
+            for args in args_list {
+                let mut p = String::from(path);
+                p.push_str("::<");
+                p.push_str(args);
+                p.push('>');
+                paths.push(p);
+            }
+        }
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+        self.dump_inline_functions(manifest_dir, dump_dir, &paths, revisions);
     }
 
     /// Adds additional command line arguments for `cargo`. (all revisions)
@@ -170,36 +958,609 @@ impl Tester {
         self.config.cargo_args.extend(args.into_iter().map(Into::into));
         self
     }
-    /// Adds additional rustflags to set. (all revisions)
+    /// Adds additional rustflags to set. (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::rustc_args`] instead.
+    pub fn rustc_args<I: IntoIterator<Item = S>, S: Into<String>>(mut self, args: I) -> Self {
+        self.config.rustc_args.extend(args.into_iter().map(Into::into));
+        self
+    }
+    /// Adds additional codegen options (`-C`) or unstable options (`-Z`) to pass to rustc. (all revisions)
+    ///
+    /// Each argument must be in the form of `-C key`, `-C key=value`, or their forms
+    /// without the space (e.g., `-Clink-dead-code`, `-Z function-sections=yes`).
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::codegen_args`] instead.
+    ///
+    /// `-C codegen-units` is ignored: asmtest always builds with a single codegen unit to get
+    /// a single object file, which overrides the value set in rustflags and the release
+    /// profile (`codegen-units` in `[profile.release]` or `CARGO_PROFILE_RELEASE_CODEGEN_UNITS`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if an argument is not well-formed.
+    pub fn codegen_args<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, args: I) -> Self {
+        self.config
+            .codegen_args
+            .extend(args.into_iter().flat_map(|a| parse_codegen_arg(a.as_ref())));
+        self
+    }
+    /// Sets the optimization level (`0`, `1`, `2`, `3`, `s`, or `z`). (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::opt_level`] instead.
+    ///
+    /// This takes precedence over the level set in the release profile.
+    pub fn opt_level<L: Into<String>>(mut self, level: L) -> Self {
+        self.config.opt_level = Some(level.into());
+        self
+    }
+    /// Sets the panic strategy. (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::panic`] instead.
+    ///
+    /// Default is the default of the target.
+    pub fn panic(mut self, strategy: Panic) -> Self {
+        self.config.panic = Some(strategy);
+        self
+    }
+    /// Ignores rustflags from the environment (`RUSTFLAGS`, etc.) and the Cargo
+    /// configuration (`build.rustflags`, etc.). (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::clean_rustflags`] instead.
+    ///
+    /// Only the flags passed via asmtest's options are used, so the generated assembly
+    /// doesn't depend on the local configuration of the developer.
+    pub fn clean_rustflags(mut self) -> Self {
+        self.config.clean_rustflags = true;
+        self
+    }
+    /// Adds additional command line arguments for objdump. (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::objdump_args`] instead.
+    pub fn objdump_args<I: IntoIterator<Item = S>, S: Into<String>>(mut self, args: I) -> Self {
+        self.config.objdump_args.extend(args.into_iter().map(Into::into));
+        self
+    }
+    /// Uses the given program instead of `objdump` or `llvm-objdump` for disassembling. (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::objdump_program`] instead.
+    ///
+    /// The program is run via `docker run` like the default ones, so it must be
+    /// available in the container and accept the same options as the program it replaces.
+    pub fn objdump_program<P: Into<String>>(mut self, program: P) -> Self {
+        self.config.objdump_program = Some(program.into());
+        self
+    }
+    /// Uses AT&T syntax in x86/x86_64 assemblies. (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::att_syntax`] instead.
+    ///
+    /// By default, Intel syntax is used to match Rust inline assembly.
+    pub fn att_syntax(mut self) -> Self {
+        self.config.att_syntax = true;
+        self
+    }
+    /// Disables pseudo-instructions (e.g., `mv`, `li`, `ret`) in RISC-V assemblies. (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::riscv_no_aliases`] instead.
+    ///
+    /// By default, pseudo-instructions are used.
+    pub fn riscv_no_aliases(mut self) -> Self {
+        self.config.riscv_no_aliases = true;
+        self
+    }
+    /// Uses numeric register names (e.g., `x10`) instead of ABI names (e.g., `a0`)
+    /// in RISC-V assemblies. (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::riscv_numeric_reg_names`] instead.
+    ///
+    /// By default, ABI names are used.
+    pub fn riscv_numeric_reg_names(mut self) -> Self {
+        self.config.riscv_numeric_reg_names = true;
+        self
+    }
+    /// Shows raw instruction bytes alongside mnemonics. (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::show_bytes`] instead.
+    ///
+    /// The bytes are emitted in a trailing column like `mov eax, esi ; 89 f0`.
+    pub fn show_bytes(mut self) -> Self {
+        self.config.show_bytes = true;
+        self
+    }
+    /// Asserts that functions whose names match `function_regex` do not contain
+    /// the given instruction. (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::forbid_instruction`] instead.
+    ///
+    /// `function_regex` is matched against the function name as it appears in the
+    /// generated assembly. This can be called multiple times to forbid multiple instructions.
+    pub fn forbid_instruction<M: Into<String>>(
+        mut self,
+        function_regex: &str,
+        mnemonic: M,
+    ) -> Self {
+        self.config
+            .forbidden_instructions
+            .push((Regex::new(function_regex).unwrap(), mnemonic.into()));
+        self
+    }
+    /// Asserts that, for each of the given regexes, at least one function whose
+    /// name matches it is emitted. (all revisions)
+    ///
+    /// This will be shared with all revisions.
+    /// If you want to apply only to a specific revision, use [`Revision::expect_functions`] instead.
+    ///
+    /// This catches functions that were unexpectedly merged or not emitted.
+    pub fn expect_functions<I: IntoIterator<Item = S>, S: AsRef<str>>(
+        mut self,
+        function_regexes: I,
+    ) -> Self {
+        self.config
+            .expected_functions
+            .extend(function_regexes.into_iter().map(|re| Regex::new(re.as_ref()).unwrap()));
+        self
+    }
+    /// Uses the given directory as the target directory for builds.
+    ///
+    /// `target_dir` is resolved to `manifest_dir.join(target_dir)`.
+    ///
+    /// By default, the target directory of the crate being tested is used. Using a separate
+    /// directory avoids interleaving `--release --emit=obj` builds with the normal builds in the
+    /// same target directory.
+    pub fn target_dir<P: Into<PathBuf>>(mut self, target_dir: P) -> Self {
+        self.target_dir = Some(target_dir.into());
+        self
+    }
+    fn resolve_target_dir(&self, manifest_dir: &Path) -> Option<PathBuf> {
+        self.target_dir.as_ref().map(|target_dir| manifest_dir.join(target_dir))
+    }
+    /// Only builds the crate for the given revisions, without disassembling
+    /// and checking the generated assemblies.
+    ///
+    /// This is useful as a fast smoke test that the crate compiles for all targets.
+    pub fn build_only(mut self) -> Self {
+        self.build_only = true;
+        self
+    }
+    /// Disassembles the object files already in the target directory instead of building
+    /// the crate.
+    ///
+    /// This is useful when the crate has been built in a separate step (e.g., in CI) with
+    /// `cargo rustc --release --target <target> -- --emit=obj` (`--emit=llvm-ir` if
+    /// [`Tester::emit_llvm_ir`] is set). Use [`Tester::target_dir`] if it was built in
+    /// a separate target directory.
+    ///
+    /// Since the build is skipped, options that affect code generation (e.g.,
+    /// [`Tester::codegen_args`] and [`Revision::target_feature`]) are not applied, and
+    /// if there are multiple object files of the crate, the most recently modified one is used.
+    pub fn prebuilt(mut self) -> Self {
+        self.prebuilt = true;
+        self
+    }
+    /// Sets the number of times to retry `docker run` when it fails to start the container.
+    ///
+    /// Failures of the command run in the container are not retried.
+    ///
+    /// Default is 2.
+    pub fn docker_retries(mut self, retries: u32) -> Self {
+        self.docker_retries = retries;
+        self
+    }
+    /// Sets the user (`--user` of `docker run`) to run commands in the container as.
+    ///
+    /// By default, the current user is used on Unix-like systems (except with rootless docker
+    /// or podman), and no user is set on Windows. Use this if files in the container cannot
+    /// be accessed due to permission errors.
+    ///
+    /// `user` is in the form accepted by `--user` (e.g., `1000:1000`).
+    pub fn docker_user<U: Into<String>>(mut self, user: U) -> Self {
+        self.docker_user = Some(user.into());
+        self
+    }
+    /// Shows compiler warnings even if the build succeeds.
+    ///
+    /// By default, the output from Cargo is only shown if the build fails.
+    pub fn show_warnings(mut self) -> Self {
+        self.show_warnings = true;
+        self
+    }
+    /// Ignores differences that consist only of changes in the amount of spaces
+    /// (outside of string literals) when comparing with the existing files.
+    ///
+    /// This is useful to avoid churn caused by column-alignment changes when
+    /// updating the objdump. Files are not rewritten if only such differences exist.
+    pub fn ignore_whitespace_churn(mut self) -> Self {
+        self.ignore_whitespace_churn = true;
+        self
+    }
+    /// Fails on differences from the existing files even outside of CI, instead of
+    /// rewriting them.
+    ///
+    /// By default, the existing files are rewritten with the generated ones unless the
+    /// `CI` environment variable is set. With this option, the diff is shown and the test fails,
+    /// and the files are only rewritten if the `ASMTEST_BLESS` environment variable is set
+    /// (e.g., `ASMTEST_BLESS=1 cargo test`).
+    ///
+    /// This can also be enabled by the `ASMTEST_NO_WRITE` environment variable.
+    pub fn no_autowrite(mut self) -> Self {
+        self.no_autowrite = true;
+        self
+    }
+    /// Rewrites the existing files with the generated ones without comparing them,
+    /// even in CI or if [`Tester::no_autowrite`] is set.
+    ///
+    /// All revisions are dumped, even if [`Tester::incremental`] is set, and the number
+    /// of changed files is reported.
+    /// This is useful to update all files after an intentional change of code generation.
+    ///
+    /// This can also be enabled by the `ASMTEST_BLESS` environment variable.
+    pub fn bless(mut self) -> Self {
+        self.bless = true;
+        self
+    }
+    /// Dumps LLVM IR instead of assemblies.
+    ///
+    /// The IR is written to `<revision>.ll` instead of `<revision>.asm` after normalization:
+    /// symbol names are demangled and metadata whose numbering changes with unrelated edits
+    /// is removed.
+    ///
+    /// Options for objdump are ignored in this mode.
+    pub fn emit_llvm_ir(mut self) -> Self {
+        self.emit_llvm_ir = true;
+        self
+    }
+    /// Sets the function to determine the path of the generated file for each revision,
+    /// relative to `dump_dir`.
+    ///
+    /// Default is `<name>.asm` (or `<name>.ll` if [`Tester::emit_llvm_ir`] is set).
+    /// [`Revision::name`] and [`Revision::target`] can be used to derive the path
+    /// (e.g., `<target>/<name>.s`). The raw output saved in the target directory
+    /// follows the same naming.
+    pub fn output_naming(mut self, f: fn(&Revision) -> PathBuf) -> Self {
+        self.output_naming = Some(f);
+        self
+    }
+    /// Emits a comment line (`; ---- <function name> ----`) before each function.
+    ///
+    /// This makes it easy to see which function changed in diffs of the generated assemblies.
+    pub fn function_markers(mut self) -> Self {
+        self.function_markers = true;
+        self
+    }
+    /// Draws ASCII arrows between jumps and their targets, using the
+    /// `--visualize-jumps` option of llvm-objdump.
+    ///
+    /// This is ignored for targets that use GNU objdump.
+    pub fn visualize_jumps(mut self) -> Self {
+        self.visualize_jumps = true;
+        self
+    }
+    /// Removes alignment padding (e.g., `nop` and `int3` on x86) at the end of each function.
+    ///
+    /// The amount of padding can change with unrelated edits, because it depends on the size
+    /// of the function and the alignment of the next function.
+    pub fn trim_padding(mut self) -> Self {
+        self.trim_padding = true;
+        self
+    }
+    /// Removes `@ imm = #..` comments added by llvm-objdump to operands of Arm instructions.
+    ///
+    /// They are redundant with the immediate already shown in the instruction.
+    /// Comments of branches to local labels are always removed along with the target address.
+    pub fn strip_imm_annotations(mut self) -> Self {
+        self.strip_imm_annotations = true;
+        self
+    }
+    /// Canonicalizes hexadecimal numbers in operands to lowercase with `0x` prefix
+    /// (e.g., `0XFF` and `ff <f+0xff>` are rendered as `0xff` and `0xff <f+0xff>`).
+    ///
+    /// The format of numbers differs between objdump implementations, so this reduces
+    /// differences between the outputs of GNU objdump and llvm-objdump.
+    pub fn canonicalize_hex(mut self) -> Self {
+        self.canonicalize_hex = true;
+        self
+    }
+    /// Canonicalizes register names in operands that GNU objdump and llvm-objdump spell
+    /// differently.
+    ///
+    /// This currently affects the following architectures:
+    ///
+    /// - 32-bit Arm: `sb`, `sl`, `fp`, and `ip` are rendered as `r9`, `r10`, `r11`, and `r12`.
+    /// - MIPS: `$zero`, `$at`, `$k0`, `$k1`, `$gp`, `$sp`, `$fp` (`$s8`), and `$ra` are rendered
+    ///   as numeric names (e.g., `$29`), like the other registers.
+    ///
+    /// Combined with [`Tester::force_gnu_for`] or [`Tester::force_llvm_for`], this allows
+    /// comparing the outputs of both implementations without differences in register naming.
+    /// Differences between syntaxes (e.g., `%rax` in AT&T syntax and `rax` in Intel syntax)
+    /// are not canonicalized.
+    pub fn canonicalize_registers(mut self) -> Self {
+        self.canonicalize_registers = true;
+        self
+    }
+    /// Removes instructions and prefixes emitted for Intel CET (`endbr64`/`endbr32`, and
+    /// `notrack`/`bnd` prefixes of branches) from x86 assembly.
+    ///
+    /// They depend on the CET settings of the target (e.g., `-Z cf-protection`), so this
+    /// makes the generated assembly independent of them.
+    pub fn strip_cet(mut self) -> Self {
+        self.strip_cet = true;
+        self
+    }
+    /// Panics if a reference to the function itself (e.g., `<f+0x10>`) remains in the operands
+    /// after branch targets are replaced with local labels.
+    ///
+    /// Such a reference means that a local label was not created for a branch within the
+    /// function, which indicates a bug in asmtest's handling of the architecture. References
+    /// left intentionally (e.g., targets omitted by [`Tester::head_instructions`]) are ignored.
+    pub fn strict_labels(mut self) -> Self {
+        self.strict_labels = true;
+        self
+    }
+    /// Shows the targets of relocations (e.g., calls to other functions) instead of the
+    /// placeholder addresses in relocatable objects.
+    ///
+    /// By default, the placeholder references of relocated instructions (e.g., the next
+    /// instruction for x86-64 `call`, or the instruction itself for AArch64 `bl`) are handled
+    /// like branches within the function, so they may get local labels pointing to unrelated
+    /// instructions. With this, objdump is run with `-r`, and they are replaced with the symbols
+    /// the relocations refer to (e.g., `<core::panicking::panic>`).
+    pub fn resolve_relocations(mut self) -> Self {
+        self.resolve_relocations = true;
+        self
+    }
+    /// Emits only the raw bytes of instructions, one instruction per line, instead of
+    /// the disassembly.
+    ///
+    /// This detects any change in the encoding of instructions, regardless of how objdump
+    /// renders them. Bytes are shown in the format printed by objdump, which may group
+    /// them into words depending on the architecture and objdump implementation.
+    pub fn bytes_only(mut self) -> Self {
+        self.bytes_only = true;
+        self
+    }
+    /// Emits the address of each instruction, relative to the start of the function,
+    /// in a leading column like `   0x4  mov eax, esi`.
+    ///
+    /// This is useful to correlate the output with the raw output of objdump or to see
+    /// the distances of branches, but changes in the size of an instruction change the
+    /// addresses of all following instructions.
+    pub fn show_addresses(mut self) -> Self {
+        self.show_addresses = true;
+        self
+    }
+    /// Sets how local labels (targets of branches within a function) are rendered.
+    ///
+    /// Default is [`LabelStyle::Numeric`].
+    pub fn label_style(mut self, style: LabelStyle) -> Self {
+        self.label_style = style;
+        self
+    }
+    /// Emits only the first `n` instructions of each function, followed by a
+    /// `; ... (truncated)` line if the rest is omitted.
+    ///
+    /// This is useful to generate compact examples (e.g., showing only prologues).
+    /// Branches to labels in the omitted part are shown with their target addresses.
+    pub fn head_instructions(mut self, n: usize) -> Self {
+        self.head_instructions = Some(n);
+        self
+    }
+    /// Panics instead of printing a warning if no functions are found in the assembly of a revision.
+    ///
+    /// This usually indicates a misconfiguration, such as a crate type that doesn't emit code,
+    /// or a crate whose functions are all generic or `#[inline]`
+    /// (see [`Tester::dump_inline_functions`]).
+    pub fn deny_empty_output(mut self) -> Self {
+        self.deny_empty_output = true;
+        self
+    }
+    /// Writes a hash of each function alongside the generated assembly
+    /// (e.g., `x86_64.sums` for `x86_64.asm`), and compares it with the existing file.
+    ///
+    /// This gives an overview of which functions changed without reading the whole diff
+    /// of the generated assemblies. The hash is stable across platforms and toolchains.
+    ///
+    /// This is ignored if [`Tester::emit_llvm_ir`] is set.
+    pub fn emit_fingerprints(mut self) -> Self {
+        self.emit_fingerprints = true;
+        self
+    }
+    /// Emits the file format and the section of each function printed by objdump as comments
+    /// (e.g., `; file format elf64-x86-64` and `; section .text.foo`).
+    ///
+    /// The section comment is emitted before a function only if its section differs from
+    /// the one of the previous function.
+    pub fn include_file_comments(mut self) -> Self {
+        self.include_file_comments = true;
+        self
+    }
+    /// Shows a summary of the changed files and functions instead of the diff of each file,
+    /// when the generated files differ from the existing files in CI or with [`Tester::no_autowrite`].
+    ///
+    /// All revisions are dumped before the test fails, so this is useful to see the
+    /// overall impact of a change that affects many revisions.
+    pub fn diff_summary(mut self) -> Self {
+        self.diff_summary = true;
+        self
+    }
+    /// Sets the function to call when a generated file differs from the existing file.
+    ///
+    /// The function is called with the name of the revision (`report` for the file written by
+    /// [`Tester::combined_report`]), the path to the existing file, and the generated contents,
+    /// before the diff is shown or the file is updated. This is useful to integrate with CI
+    /// (e.g., uploading the generated file as an artifact).
+    ///
+    /// The function is not called if [`Tester::bless`] is set.
+    pub fn on_mismatch<F: Fn(&str, &Path, &[u8]) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.on_mismatch = Some(OnMismatch(Box::new(f)));
+        self
+    }
+    /// Writes the diagnostics (warnings and errors) emitted by the compiler for the crate being
+    /// tested to `<revision>.stderr` next to the generated assembly, and checks it like
+    /// the assembly.
+    ///
+    /// If the build fails with errors, only the diagnostics are checked for that revision,
+    /// so tests can also assert that a configuration is rejected at compile time.
+    /// The file is not created if no diagnostics are emitted.
+    pub fn capture_diagnostics(mut self) -> Self {
+        self.capture_diagnostics = true;
+        self
+    }
+    /// Sets the time limit for building and disassembling each revision.
+    ///
+    /// If exceeded, the running processes (e.g., `cargo build` and `docker run`) are killed
+    /// and this panics with the name of the revision that timed out.
+    ///
+    /// Default is no limit.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    /// Skips revisions whose inputs have not changed since the last run.
+    ///
+    /// The inputs include the options, the toolchain, the Cargo configuration, and all
+    /// files in the workspace of the crate. A revision is rebuilt if any of them changed,
+    /// or if the output file has been modified or removed since it was generated.
+    ///
+    /// Changes to path dependencies outside the workspace are not detected.
+    ///
+    /// This is ignored if [`Tester::build_only`] is set.
+    pub fn incremental(mut self) -> Self {
+        self.incremental = true;
+        self
+    }
+    /// Asserts that the output of `<program> --version` in the container contains `version`.
+    ///
+    /// The normalized output depends on the exact version of objdump, so this detects
+    /// unexpected updates of the docker image before they change the generated files.
+    ///
+    /// For example, `.expect_tool_version("llvm-objdump", "LLVM version 22.")`.
+    pub fn expect_tool_version<P: Into<String>, V: Into<String>>(
+        mut self,
+        program: P,
+        version: V,
+    ) -> Self {
+        self.expected_tool_versions.push((program.into(), version.into()));
+        self
+    }
+    /// Sets the tool used to show the diff when the generated assembly differs from the
+    /// file on CI.
+    ///
+    /// Default is [`DiffTool::Git`].
+    pub fn diff_tool(mut self, tool: DiffTool) -> Self {
+        self.diff_tool = tool;
+        self
+    }
+    /// Shows diffs without color, even if the output is a terminal or the test runs on
+    /// GitHub Actions.
+    ///
+    /// This can also be enabled by setting the `NO_COLOR` environment variable to a non-empty value.
+    /// When [`DiffTool::Delta`] is used, diffs are shown by `git diff` instead.
+    pub fn no_color(mut self) -> Self {
+        self.no_color = true;
+        self
+    }
+    /// Uses GNU objdump instead of llvm-objdump for the given architectures (`target_arch`
+    /// values such as `"riscv64"` or `"mips"`).
+    ///
+    /// By default, GNU objdump is used for architectures where llvm-objdump is known to
+    /// decode or print some instructions incorrectly, and llvm-objdump is used for others.
+    /// This overrides the default, and later calls of this and [`Tester::force_llvm_for`]
+    /// take precedence over earlier ones.
+    pub fn force_gnu_for<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, arches: I) -> Self {
+        self.objdump_overrides.extend(arches.into_iter().map(|a| (a.as_ref().into(), true)));
+        self
+    }
+    /// Uses llvm-objdump instead of GNU objdump for the given architectures (`target_arch`
+    /// values such as `"sparc64"` or `"m68k"`).
+    ///
+    /// See [`Tester::force_gnu_for`] for details.
+    pub fn force_llvm_for<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, arches: I) -> Self {
+        self.objdump_overrides.extend(arches.into_iter().map(|a| (a.as_ref().into(), false)));
+        self
+    }
+    /// Sets the host triple used to resolve the Cargo configuration
+    /// (e.g., `[target.<triple>]` tables for the host).
+    ///
+    /// Default is the host triple of the compiler used to build this library.
+    pub fn host_triple<T: Into<String>>(mut self, triple: T) -> Self {
+        self.host_triple = Some(triple.into());
+        self
+    }
+    /// Runs `cargo clean` for the package before building each revision.
     ///
-    /// This will be shared with all revisions.
-    /// If you want to apply only to a specific revision, use [`Revision::rustc_args`] instead.
-    pub fn rustc_args<I: IntoIterator<Item = S>, S: Into<String>>(mut self, args: I) -> Self {
-        self.config.rustc_args.extend(args.into_iter().map(Into::into));
+    /// This guarantees that the assembly is generated from a fresh build, at the cost of build time.
+    pub fn clean(mut self) -> Self {
+        self.clean = true;
         self
     }
-    /// Adds additional command line arguments for objdump. (all revisions)
+    /// Ignores environment variables that affect code generation (e.g., `RUSTFLAGS`,
+    /// `CARGO_INCREMENTAL`, `CARGO_PROFILE_*`, and `RUSTC_WRAPPER`) when building.
     ///
-    /// This will be shared with all revisions.
-    /// If you want to apply only to a specific revision, use [`Revision::objdump_args`] instead.
-    pub fn objdump_args<I: IntoIterator<Item = S>, S: Into<String>>(mut self, args: I) -> Self {
-        self.config.objdump_args.extend(args.into_iter().map(Into::into));
+    /// This makes the generated assembly independent of the shell that runs the test.
+    /// The Cargo configuration is still respected; use [`Tester::clean_rustflags`] to also
+    /// ignore rustflags from it. Flags that enable instrumentation (`-C instrument-coverage` and
+    /// `-C profile-generate`) are removed even if they are set in the Cargo configuration;
+    /// without this option, a warning is printed for them. `RUSTFLAGS` is not passed to Cargo
+    /// even without this option, because it is already included in the rustflags asmtest passes.
+    pub fn reproducible(mut self) -> Self {
+        self.reproducible = true;
         self
     }
-    /// Uses AT&T syntax in x86/x86_64 assemblies. (all revisions)
+    /// Writes the functions whose names match the given regex from all revisions
+    /// into a single file (`report.asm` in the `dump_dir`), with a section per revision.
     ///
-    /// This will be shared with all revisions.
-    /// If you want to apply only to a specific revision, use [`Revision::att_syntax`] instead.
+    /// This is useful to compare the assembly of a function across targets
+    /// (e.g., a CAS loop on every architecture). The file is checked like the
+    /// files of each revision.
     ///
-    /// By default, Intel syntax is used to match Rust inline assembly.
-    pub fn att_syntax(mut self) -> Self {
-        self.config.att_syntax = true;
+    /// Functions are sorted by name in each section, so the order doesn't depend on the order
+    /// of symbols in the object file.
+    ///
+    /// The file is not written if revisions are selected by tags (see [`Tester::only_tags`]),
+    /// or [`Tester::build_only`] or [`Tester::emit_llvm_ir`] is set.
+    pub fn combined_report(mut self, function_regex: &str) -> Self {
+        self.combined_report = Some(Regex::new(function_regex).unwrap());
+        self
+    }
+    /// Only runs revisions that have at least one of the given tags (see [`Revision::tag`]).
+    ///
+    /// This can be overridden by the `ASMTEST_TAGS` environment variable, which takes
+    /// a comma-separated list of tags (e.g., `ASMTEST_TAGS=aarch64,riscv`).
+    pub fn only_tags<I: IntoIterator<Item = S>, S: Into<String>>(mut self, tags: I) -> Self {
+        self.only_tags.extend(tags.into_iter().map(Into::into));
         self
     }
 }
 
-fn dump(tester: &Tester, manifest_dir: &Path, dump_dir: &Path, revisions: &[Revision]) {
-    let tcx = &TesterContext::new(tester, manifest_dir);
+/// Parses `-C key[=value]` or `-Z key[=value]` into a pair of the flag and the option.
+fn parse_codegen_arg(arg: &str) -> [String; 2] {
+    let arg = arg.trim_ascii();
+    let (flag, option) = match arg.get(..2) {
+        Some(flag @ ("-C" | "-Z")) => (flag, arg[2..].trim_ascii_start()),
+        _ => panic!("codegen argument `{arg}` must start with `-C` or `-Z`"),
+    };
+    let key = option.split_once('=').map_or(option, |(key, _)| key);
+    if key.is_empty() || !key.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_'))
+    {
+        panic!("codegen argument `{arg}` is not in the form of `{flag} key` or `{flag} key=value`");
+    }
+    [flag.into(), option.into()]
+}
+
+fn dump(tcx: &TesterContext<'_>, dump_dir: &Path, revisions: &[Revision]) {
     let manifest_dir = Path::new(&tcx.manifest_path).parent().unwrap();
     let dump_dir = manifest_dir.join(dump_dir);
     let raw_dump_dir = tcx
@@ -207,119 +1568,442 @@ fn dump(tester: &Tester, manifest_dir: &Path, dump_dir: &Path, revisions: &[Revi
         .target_directory
         .join("tests/asmtest/raw")
         .join(dump_dir.strip_prefix(manifest_dir).unwrap());
+    dump_revisions(tcx, &dump_dir, &raw_dump_dir, revisions);
+}
 
-    let mut cargo_base_args = vec!["rustc", "--release", "--manifest-path", &tcx.manifest_path];
-    let mut cargo_base_rest_args = vec!["--", "--emit=obj"];
-    if !tcx.tester.config.cargo_args.is_empty() {
-        let mut base_args = &mut cargo_base_args;
-        for arg in &tcx.tester.config.cargo_args {
-            if arg == "--" {
-                base_args = &mut cargo_base_rest_args;
-            } else {
-                base_args.push(arg);
-            }
+fn dump_revisions(
+    tcx: &TesterContext<'_>,
+    dump_dir: &Path,
+    raw_dump_dir: &Path,
+    revisions: &[Revision],
+) {
+    for (i, revision) in revisions.iter().enumerate() {
+        if revisions[..i].iter().any(|r| r.name == revision.name) {
+            panic!("revision name `{}` is used more than once", revision.name);
         }
     }
-
-    fs::create_dir_all(&dump_dir).unwrap();
-    fs::create_dir_all(&raw_dump_dir).unwrap();
+    let env_tags = env::var("ASMTEST_TAGS").ok().filter(|v| !v.is_empty());
+    let only_tags: Vec<&str> = match &env_tags {
+        Some(tags) => tags.split(',').map(str::trim_ascii).collect(),
+        None => tcx.tester.only_tags.iter().map(String::as_str).collect(),
+    };
+    let revisions: Vec<&Revision> = revisions
+        .iter()
+        .filter(|r| only_tags.is_empty() || r.tags.iter().any(|t| only_tags.contains(&&**t)))
+        .collect();
+    if !tcx.tester.build_only {
+        fs::create_dir_all(dump_dir).unwrap();
+    }
+    let report = tcx
+        .tester
+        .combined_report
+        .as_ref()
+        .filter(|_| only_tags.is_empty() && !tcx.tester.build_only && !tcx.tester.emit_llvm_ir);
+    let mut outs = vec![];
+    // Hashes of the sources, shared between revisions of the same workspace.
+    let mut sources = vec![];
+    let ext = if tcx.tester.emit_llvm_ir { ".ll" } else { ".asm" };
     for revision in revisions {
         eprintln!("testing revision {}", revision.name);
-        // Get target info.
-        let target = TargetTripleRef::from(&revision.target);
-        let target_name = target.triple();
-        let target_arch = tcx.config.cfg::<TargetArch, _>(&target).unwrap();
-        let is_powerpc64be = target_arch == TargetArch::powerpc64
-            && tcx.config.cfg::<TargetEndian, _>(&target).unwrap() == TargetEndian::big;
-        let mut cx = RevisionContext {
+        let path = match tcx.tester.output_naming {
+            Some(f) => f(revision),
+            None => PathBuf::from(revision.name.clone() + ext),
+        };
+        let raw_dump_path = raw_dump_dir.join(&path);
+        if !tcx.tester.build_only {
+            fs::create_dir_all(raw_dump_path.parent().unwrap()).unwrap();
+        }
+        let dump_path = dump_dir.join(&path);
+        let sums_path = (tcx.tester.emit_fingerprints && !tcx.tester.emit_llvm_ir)
+            .then(|| dump_path.with_extension("sums"));
+        let mut stamp_path = raw_dump_path.clone().into_os_string();
+        stamp_path.push(".fingerprint");
+        let stamp_path = PathBuf::from(stamp_path);
+        let fingerprint = (tcx.tester.incremental && !tcx.tester.build_only)
+            .then(|| incremental::fingerprint(tcx, revision, dump_dir, &mut sources));
+        if let Some(fingerprint) = fingerprint {
+            if !tcx.bless
+                && incremental::is_fresh(&stamp_path, fingerprint, &dump_path)
+                && sums_path.as_ref().is_none_or(|p| p.is_file())
+            {
+                eprintln!("skipping revision {} (unchanged)", revision.name);
+                if report.is_some() {
+                    outs.push((revision, fs::read_to_string(&dump_path).unwrap()));
+                }
+                continue;
+            }
+        }
+        let mut diagnostics = String::new();
+        let out = dump_revision(
             tcx,
-            prefer_gnu: false, // TODO: make this an option
             revision,
-            target_name,
-            arch_family: ArchFamily::new(&target_arch),
-            is_powerpc64be,
-            obj_path: PathBuf::new(),
-            verbose_function_names: vec![],
-            out: String::new(),
-        };
+            Some(&raw_dump_path),
+            None,
+            tcx.tester.capture_diagnostics.then_some(&mut diagnostics),
+        );
+        if tcx.tester.capture_diagnostics {
+            let stderr_path = dump_path.with_extension("stderr");
+            if !diagnostics.is_empty() || stderr_path.is_file() {
+                assert_diff(tcx, &revision.name, stderr_path, &diagnostics);
+            }
+        }
+        let Some(out) = out else { continue };
+
+        // Check output.
+        let changed = tcx.changed.borrow().len();
+        assert_diff(tcx, &revision.name, &dump_path, &out);
+        if let Some(sums_path) = &sums_path {
+            assert_diff(tcx, &revision.name, sums_path, function_sums(&out));
+        }
+        // Don't mark as fresh if the difference is reported later by Tester::diff_summary.
+        if let Some(fingerprint) = fingerprint.filter(|_| tcx.changed.borrow().len() == changed) {
+            incremental::write_stamp(&stamp_path, fingerprint, &dump_path);
+        }
+        if report.is_some() {
+            outs.push((revision, out));
+        }
+    }
+    if let Some(function_re) = report {
+        assert_diff(
+            tcx,
+            "report",
+            dump_dir.join("report.asm"),
+            combined_report(function_re, &outs),
+        );
+    }
+    if tcx.bless {
+        eprintln!("blessed {} file(s) in {}", tcx.blessed.replace(0), dump_dir.display());
+    }
+    let changed = tcx.changed.take();
+    if !changed.is_empty() {
+        eprintln!("{} file(s) differ from the generated ones:", changed.len());
+        for (path, functions) in &changed {
+            let path = path.strip_prefix(dump_dir).unwrap_or(path).display();
+            if functions.is_empty() {
+                eprintln!("  {path}");
+            } else {
+                let n = functions.len();
+                eprintln!("  {path}: {n} function(s) changed ({})", functions.join(", "));
+            }
+        }
+        panic!(
+            "assertion failed; run test without Tester::diff_summary to see the diffs, or with `ASMTEST_BLESS=1` to update the files"
+        );
+    }
+}
+
+/// Returns the contents of the file written by [`Tester::emit_fingerprints`].
+fn function_sums(out: &str) -> String {
+    use core::fmt::Write as _;
+    let mut sums = String::new();
+    for func in out.split("\n\n") {
+        // Skip the comment emitted by Tester::function_markers.
+        let mut lines = func.lines().skip_while(|line| line.starts_with(';'));
+        let Some(name) = lines.next().and_then(|line| line.strip_suffix(':')) else { continue };
+        let mut h = Fnv1a::new();
+        for line in lines {
+            h.write(line.as_bytes());
+            h.write(b"\n");
+        }
+        let _ = writeln!(sums, "{:016x}  {name}", h.finish());
+    }
+    sums
+}
+
+/// FNV-1a, which is simple and stable across platforms and toolchains, unlike the hasher
+/// of std.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xCBF2_9CE4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x0100_0000_01B3);
+        }
+    }
+}
+
+/// Returns the contents of the file written by [`Tester::combined_report`].
+fn combined_report(function_re: &Regex, outs: &[(&Revision, String)]) -> String {
+    let mut report = String::new();
+    for (revision, out) in outs {
+        if !report.is_empty() {
+            report.push('\n');
+        }
+        report.push_str("; ==== ");
+        report.push_str(&revision.name);
+        report.push_str(" (");
+        report.push_str(&revision.target);
+        report.push_str(") ====\n");
+        let mut functions: Vec<(&str, &str)> = out
+            .split("\n\n")
+            .filter_map(|func| {
+                // Skip the comment emitted by Tester::function_markers.
+                let name = func
+                    .lines()
+                    .find(|line| !line.starts_with(';'))
+                    .and_then(|line| line.strip_suffix(':'))
+                    .filter(|name| function_re.is_match(name))?;
+                Some((name, func.trim_end_matches('\n')))
+            })
+            .collect();
+        // Sort by name, and by contents for functions with the same name, so that the order
+        // doesn't depend on the order of symbols in the object.
+        functions.sort_unstable();
+        let functions: Vec<&str> = functions.into_iter().map(|(_, func)| func).collect();
+        if functions.is_empty() {
+            report.push_str("; no matching function\n");
+        } else {
+            report.push_str(&functions.join("\n\n"));
+            report.push('\n');
+        }
+    }
+    report
+}
+
+/// Builds and disassembles the given revision, and returns the normalized assembly
+/// (or LLVM IR if [`Tester::emit_llvm_ir`] is set).
+///
+/// If `parsed` is `Some`, the parsed instructions of each function are stored in it.
+///
+/// Returns `None` if [`Tester::build_only`] is set.
+fn dump_revision(
+    tcx: &TesterContext<'_>,
+    revision: &Revision,
+    raw_dump_path: Option<&Path>,
+    parsed: Option<&mut Vec<(String, Vec<Asm>)>>,
+    diagnostics: Option<&mut String>,
+) -> Option<String> {
+    let revision_tcx = tcx.for_revision(revision);
+    let tcx = revision_tcx.as_ref().unwrap_or(tcx);
+    let target = TargetTripleRef::from(&revision.target);
+    let mut cx = RevisionContext::new(tcx, revision, &target);
+    cx.parsed = parsed.is_some().then(Vec::new);
+    cx.diagnostics = diagnostics.is_some().then(String::new);
+
+    // Build and handle messages from Cargo.
+    let (cargo_base_args, cargo_base_rest_args) = tcx.cargo_base_args();
+    let built = cargo::build(&mut cx, &cargo_base_args, &cargo_base_rest_args);
+    if let Some(diagnostics) = diagnostics {
+        *diagnostics = cx.diagnostics.take().unwrap();
+    }
+    if tcx.tester.build_only || !built {
+        return None;
+    }
 
-        // Build and handle messages from Cargo.
-        cargo::build(&mut cx, &cargo_base_args, &cargo_base_rest_args);
+    if tcx.tester.emit_llvm_ir {
+        let raw_out = llvm_ir::read(&cx);
+        if let Some(raw_dump_path) = raw_dump_path {
+            // Save raw IR to target directory for debugging.
+            fs::write(raw_dump_path, &raw_out).unwrap();
+        }
+        llvm_ir::handle_ir(&mut cx, &raw_out);
+        return Some(cx.out);
+    }
 
-        // Disassemble and handle output.
-        let raw_out = objdump::disassemble(&mut cx);
+    // Disassemble and handle output.
+    let raw_out = objdump::disassemble(&mut cx);
+    if let Some(raw_dump_path) = raw_dump_path {
         // Save raw assembly to target directory for debugging.
-        fs::write(raw_dump_dir.join(revision.name.clone() + ".asm"), &raw_out).unwrap();
-        objdump::handle_asm(&mut cx, &raw_out);
+        fs::write(raw_dump_path, &raw_out).unwrap();
+    }
+    objdump::handle_asm(&mut cx, &raw_out);
+    if let Some(parsed) = parsed {
+        *parsed = cx.parsed.unwrap();
+    }
+    Some(cx.out)
+}
 
-        // Check output.
-        assert_diff(cx.tcx, dump_dir.join(revision.name.clone() + ".asm"), cx.out);
+/// The panic strategy. See [`Revision::panic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Panic {
+    /// `-C panic=abort`
+    Abort,
+    /// `-C panic=unwind`
+    Unwind,
+}
+
+impl Panic {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Abort => "abort",
+            Self::Unwind => "unwind",
+        }
     }
 }
 
+/// The tool used to show diffs. See [`Tester::diff_tool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DiffTool {
+    /// `git diff --no-index`, run in the container.
+    Git,
+    /// [delta](https://github.com/dandavison/delta), run on the host.
+    Delta,
+    /// [difftastic](https://github.com/Wilfred/difftastic), run on the host.
+    Difft,
+}
+
+/// How local labels are rendered. See [`Tester::label_style`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LabelStyle {
+    /// `<n>:`, referenced as `<n>f` (forward) or `<n>b` (backward), like local labels in
+    /// inline assembly.
+    Numeric,
+    /// `.LBB<f>_<n>:`, referenced by the same name, like labels of basic blocks in
+    /// compiler-generated assembly.
+    ///
+    /// `<f>` is the index of the function in the generated file, so labels are unique
+    /// across functions.
+    Dotted,
+}
+
+/// A line of the parsed assembly. See [`Tester::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Asm {
+    /// An instruction.
+    #[non_exhaustive]
+    Instruction {
+        /// The address of the instruction.
+        address: u64,
+        /// The mnemonic of the instruction.
+        ///
+        /// This may be a prefix (e.g., `lock` on x86) or a packet marker (e.g., `{` on Hexagon),
+        /// or empty (e.g., for non-first instructions of a packet on Hexagon).
+        mnemonic: String,
+        /// The operands of the instruction.
+        operands: String,
+    },
+    /// A local label that is a target of branches, rendered according to [`Tester::label_style`]
+    /// in the generated files (e.g., `<id>:` with [`LabelStyle::Numeric`]).
+    #[non_exhaustive]
+    Label {
+        /// The number of the label.
+        id: u32,
+    },
+}
+
+const DOCKER_IMAGE: &str = "ghcr.io/taiki-e/objdump@sha256:07e9b142237da061832dc6954fd51c86f2fa6916c5711f09d6b1d5edea408312"; // binutils-2.46.0-llvm-22
+
+/// The exit code of `docker run` (and `podman run`) when the error is with the
+/// container engine itself, rather than the command run in the container.
+const DOCKER_ERROR_EXIT_CODE: i32 = 125;
+
 struct TesterContext<'a> {
     tester: &'a Tester,
     // For Cargo
     manifest_path: String,
+    target_dir: Option<PathBuf>,
     config: cargo::Config,
     nightly: bool,
     metadata: cargo::Metadata,
+    /// Additional arguments passed to rustc when building the crate (not its dependencies).
+    rustc_args: &'static [&'static str],
     // For docker
-    docker: PathBuf,
-    user: Option<String>,
+    docker: Docker,
+    /// Whether to bless the generated files. See [`Tester::bless`].
+    bless: bool,
+    /// The number of files changed by blessing.
+    blessed: Cell<usize>,
+    /// Pairs of the file that differs from the generated one and the names of the changed
+    /// functions in it, collected for [`Tester::diff_summary`].
+    changed: RefCell<Vec<(PathBuf, Vec<String>)>>,
+    /// Pairs of the arguments passed to `rustc --print cfg` and the target features in its
+    /// output, cached across revisions.
+    target_features: RefCell<Vec<(Vec<String>, Vec<String>)>>,
 }
 
 impl<'a> TesterContext<'a> {
-    fn new(tester: &'a Tester, manifest_dir: &Path) -> Self {
-        // For Cargo
+    fn new(
+        tester: &'a Tester,
+        manifest_dir: &Path,
+        target_dir: Option<PathBuf>,
+        docker: Docker,
+    ) -> Self {
         let manifest_path = cargo::locate_project(&manifest_dir.join("Cargo.toml")).unwrap(); // Get the absolute path to the manifest.
-        let metadata = cargo::metadata(&manifest_path).unwrap();
-        let config = cargo::config(manifest_dir).unwrap();
+        let metadata = cargo::metadata(&manifest_path, target_dir.as_deref()).unwrap();
+        let config =
+            cargo::config(manifest_dir, tester.host_triple.as_deref(), tester.reproducible)
+                .unwrap();
         let rustc_version = config.rustc_version().unwrap();
-
-        // For docker
-        let docker = env::var_os("ASMTEST_DOCKER_PATH").filter(|v| !v.is_empty());
-        let docker_path_specified = docker.is_some();
-        let mut docker = docker.unwrap_or_else(|| OsString::from("docker"));
-        let mut docker_version = cmd!(&docker, "--version").read();
-        if docker_version.is_err() && !docker_path_specified {
-            docker = OsString::from("podman");
-            docker_version = cmd!(&docker, "--version").read();
-        }
-        let rootless =
-            if docker_version.expect("asmtest requires docker or podman").contains("podman") {
-                cmd!(&docker, "info").read().unwrap().contains("rootless: true")
-            } else {
-                cmd!(&docker, "info", "-f", "{{println .SecurityOptions}}")
-                    .read()
-                    .unwrap()
-                    .contains("rootless")
-            };
-        let user = if rootless {
-            None
-        } else {
-            #[cfg(not(windows))]
-            let user = {
-                format!(
-                    "{}:{}",
-                    rustix::process::getuid().as_raw(),
-                    rustix::process::getgid().as_raw()
-                )
-            };
-            #[cfg(windows)]
-            let user = "1000:1000".to_owned();
-            Some(user)
-        };
-
         Self {
             tester,
             manifest_path,
+            target_dir,
             config,
             nightly: rustc_version.nightly,
             metadata,
-            docker: docker.into(),
-            user,
+            rustc_args: &[],
+            docker,
+            bless: tester.bless || env_set("ASMTEST_BLESS"),
+            blessed: Cell::new(0),
+            changed: RefCell::new(vec![]),
+            target_features: RefCell::new(vec![]),
+        }
+    }
+    /// Asserts the versions of the programs passed to [`Tester::expect_tool_version`].
+    ///
+    /// This starts a container for each program, so this is called once per call of the
+    /// public API instead of for each context.
+    fn check_tool_versions(&self) {
+        if self.tester.build_only {
+            return;
+        }
+        let manifest_dir = Path::new(&self.manifest_path).parent().unwrap();
+        for (program, expected) in &self.tester.expected_tool_versions {
+            let version = self
+                .docker_cmd(manifest_dir, None)
+                .args([program, "--version"])
+                .read_with_retry(self.tester.docker_retries, &[DOCKER_ERROR_EXIT_CODE])
+                .unwrap();
+            assert!(
+                version.contains(&**expected),
+                "unexpected version of `{program}` in the container; the docker image may have been updated\n\
+                 expected version containing: {expected}\n\
+                 actual version:\n{version}"
+            );
+        }
+    }
+    /// Returns the directory of the crate to build for the given revision, if it is
+    /// overridden by [`Revision::manifest_dir`].
+    fn revision_manifest_dir(&self, revision: &Revision) -> Option<PathBuf> {
+        let dir = revision.manifest_dir.as_ref()?;
+        Some(Path::new(&self.manifest_path).parent().unwrap().join(dir))
+    }
+    /// Returns the context for the crate at [`Revision::manifest_dir`], if it is set.
+    fn for_revision(&self, revision: &Revision) -> Option<Self> {
+        let manifest_dir = self.revision_manifest_dir(revision)?;
+        let target_dir = self.tester.resolve_target_dir(&manifest_dir);
+        Some(Self::new(self.tester, &manifest_dir, target_dir, self.docker.clone()))
+    }
+    fn cargo_base_args(&self) -> (Vec<&str>, Vec<&str>) {
+        let mut cargo_base_args =
+            vec!["rustc", "--release", "--manifest-path", &self.manifest_path];
+        let emit = if self.tester.emit_llvm_ir { "--emit=llvm-ir" } else { "--emit=obj" };
+        let mut cargo_base_rest_args = vec!["--", emit];
+        cargo_base_rest_args.extend_from_slice(self.rustc_args);
+        if !self.tester.config.cargo_args.is_empty() {
+            let mut base_args = &mut cargo_base_args;
+            for arg in &self.tester.config.cargo_args {
+                if arg == "--" {
+                    base_args = &mut cargo_base_rest_args;
+                } else {
+                    base_args.push(arg);
+                }
+            }
         }
+        (cargo_base_args, cargo_base_rest_args)
     }
 
     // Refs:
@@ -327,7 +2011,6 @@ impl<'a> TesterContext<'a> {
     // - https://docs.podman.io/en/latest/markdown/podman-run.1.html
     // - https://cheatsheetseries.owasp.org/cheatsheets/Docker_Security_Cheat_Sheet.html
     fn docker_cmd(&self, workdir: &Path, stdin: Option<Stdio>) -> ProcessBuilder {
-        const IMAGE: &str = "ghcr.io/taiki-e/objdump@sha256:07e9b142237da061832dc6954fd51c86f2fa6916c5711f09d6b1d5edea408312"; // binutils-2.46.0-llvm-22
         let mount = {
             const PRE: &str = "type=bind,source=";
             const MID: &str = ",target=";
@@ -343,7 +2026,7 @@ impl<'a> TesterContext<'a> {
             m
         };
         let mut cmd = cmd!(
-            &self.docker,
+            &self.docker.path,
             "run",
             "--rm",
             "--init",
@@ -360,52 +2043,226 @@ impl<'a> TesterContext<'a> {
             cmd.arg("-i");
             cmd.stdin(stdin);
         }
-        if let Some(user) = &self.user {
+        if let Some(user) = self.tester.docker_user.as_ref().or(self.docker.user.as_ref()) {
             cmd.arg("--user");
             cmd.arg(user);
         }
-        cmd.arg(IMAGE);
+        cmd.arg(DOCKER_IMAGE);
         cmd
     }
 }
 
+#[derive(Clone)]
+struct Docker {
+    path: PathBuf,
+    user: Option<String>,
+}
+
+impl Docker {
+    /// Finds the container engine, and returns its path and version.
+    fn find() -> (OsString, anyhow::Result<String>) {
+        let docker = env::var_os("ASMTEST_DOCKER_PATH").filter(|v| !v.is_empty());
+        let docker_path_specified = docker.is_some();
+        let mut docker = docker.unwrap_or_else(|| OsString::from("docker"));
+        let mut docker_version = cmd!(&docker, "--version").read();
+        if docker_version.is_err() && !docker_path_specified {
+            docker = OsString::from("podman");
+            docker_version = cmd!(&docker, "--version").read();
+        }
+        (docker, docker_version)
+    }
+
+    fn detect() -> Self {
+        let (docker, docker_version) = Self::find();
+        let rootless =
+            if docker_version.expect("asmtest requires docker or podman").contains("podman") {
+                cmd!(&docker, "info").read().unwrap().contains("rootless: true")
+            } else {
+                cmd!(&docker, "info", "-f", "{{println .SecurityOptions}}")
+                    .read()
+                    .unwrap()
+                    .contains("rootless")
+            };
+        let user = if rootless {
+            None
+        } else {
+            #[cfg(not(windows))]
+            let user = Some(format!(
+                "{}:{}",
+                rustix::process::getuid().as_raw(),
+                rustix::process::getgid().as_raw()
+            ));
+            // Docker Desktop on Windows runs containers in a VM, and files in bind mounts
+            // are accessible regardless of the user in the container.
+            #[cfg(windows)]
+            let user = None;
+            user
+        };
+        Self { path: docker.into(), user }
+    }
+}
+
 struct RevisionContext<'a> {
     tcx: &'a TesterContext<'a>,
-    prefer_gnu: bool, // TODO: move to config
+    deadline: Option<Instant>,
+    nightly: bool,
+    prefer_gnu: bool,
     revision: &'a Revision,
     target_name: &'a str,
-    arch_family: ArchFamily<'a>,
-    is_powerpc64be: bool,
+    arch_family: ArchFamily,
+    big_endian: bool,
     obj_path: PathBuf,
-    verbose_function_names: Vec<String>,
+    function_names: Vec<String>,
+    parsed: Option<Vec<(String, Vec<Asm>)>>,
+    diagnostics: Option<String>,
     out: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum ArchFamily<'a> {
+impl<'a> RevisionContext<'a> {
+    fn new(
+        tcx: &'a TesterContext<'a>,
+        revision: &'a Revision,
+        target: &'a TargetTripleRef<'_>,
+    ) -> Self {
+        // Get target info.
+        let arch = ArchInfo::new(&tcx.config, target);
+        let nightly = match &revision.rustc {
+            Some(rustc) => cargo::is_nightly(rustc).unwrap(),
+            None => tcx.nightly,
+        };
+        Self {
+            tcx,
+            deadline: tcx.tester.timeout.map(|timeout| Instant::now() + timeout),
+            nightly,
+            prefer_gnu: false,
+            revision,
+            target_name: target.triple(),
+            arch_family: arch.family,
+            big_endian: arch.big_endian,
+            obj_path: PathBuf::new(),
+            function_names: vec![],
+            parsed: None,
+            diagnostics: None,
+            out: String::new(),
+        }
+    }
+
+    /// Returns `true` if the given option is enabled for all revisions or for this revision.
+    fn enabled(&self, f: fn(&CommonConfig) -> bool) -> bool {
+        f(&self.tcx.tester.config) || f(&self.revision.config)
+    }
+
+    /// Panics with the name of this revision if the given result is an error caused by
+    /// [`Tester::timeout`].
+    fn check_timeout<T>(&self, res: &anyhow::Result<T>) {
+        if let Err(e) = res {
+            if e.root_cause()
+                .downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::TimedOut)
+            {
+                panic!(
+                    "revision {} timed out after {:?}",
+                    self.revision.name,
+                    self.tcx.tester.timeout.unwrap()
+                );
+            }
+        }
+    }
+}
+
+/// Information about the architecture of a target. See [`detect_arch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ArchInfo {
+    /// The value of `cfg(target_arch)` (e.g., `"x86_64"`).
+    pub arch: String,
+    /// The family of the architecture.
+    pub family: ArchFamily,
+    /// `true` if the target is big-endian.
+    pub big_endian: bool,
+}
+
+impl ArchInfo {
+    fn new(config: &cargo::Config, target: &TargetTripleRef<'_>) -> Self {
+        let target_arch = config.cfg::<TargetArch, _>(target).unwrap();
+        Self {
+            arch: target_arch.as_str().into(),
+            family: ArchFamily::new(&target_arch),
+            big_endian: config.cfg::<TargetEndian, _>(target).unwrap() == TargetEndian::big,
+        }
+    }
+}
+
+/// Returns information about the architecture of the given target.
+///
+/// This is useful to run architecture-specific checks on the result of
+/// [`Tester::parse`] or [`Tester::dump_to_string`].
+///
+/// The Cargo configuration is loaded from the current directory, so custom targets
+/// are resolved in the same way as in builds.
+#[must_use]
+pub fn detect_arch(target: &str) -> ArchInfo {
+    let config = cargo::config(&env::current_dir().unwrap(), None, false).unwrap();
+    ArchInfo::new(&config, &TargetTripleRef::from(target))
+}
+
+/// Returns `true` if the container engine used by [`Tester::dump`] is available.
+///
+/// The container engine is docker or podman, or the program specified by the
+/// `ASMTEST_DOCKER_PATH` environment variable. This is useful to skip tests in
+/// environments without a container engine.
+#[must_use]
+pub fn container_engine_available() -> bool {
+    let (docker, docker_version) = Docker::find();
+    docker_version.is_ok() && cmd!(&docker, "info").read().is_ok()
+}
+
+/// The family of an architecture. See [`ArchInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArchFamily {
+    /// x86 and x86_64.
     X86,
+    /// Hexagon.
     Hexagon,
+    /// 32-bit Arm.
     Arm,
+    /// AArch64.
+    AArch64,
+    /// AVR.
     Avr,
+    /// C-SKY.
     CSky,
+    /// 32-bit and 64-bit LoongArch.
     LoongArch,
+    /// MSP430.
     Msp430,
+    /// 32-bit and 64-bit PowerPC.
     PowerPC,
+    /// 32-bit and 64-bit RISC-V.
+    RiscV,
+    /// 32-bit and 64-bit SPARC.
     Sparc,
+    /// 32-bit and 64-bit MIPS, including release 6.
     Mips,
+    /// M68k.
     M68k,
+    /// s390x.
     S390x,
+    /// Xtensa.
     Xtensa,
-    // Architectures that don't need special handling inside loop in handle_asm/write_func.
-    Other(&'a TargetArch),
+    /// Other architectures.
+    Other,
 }
 
-impl<'a> ArchFamily<'a> {
-    fn new(target_arch: &'a TargetArch) -> Self {
+impl ArchFamily {
+    fn new(target_arch: &TargetArch) -> Self {
         match target_arch {
             TargetArch::x86 | TargetArch::x86_64 => ArchFamily::X86,
             TargetArch::hexagon => ArchFamily::Hexagon,
             TargetArch::arm => ArchFamily::Arm,
+            TargetArch::aarch64 => ArchFamily::AArch64,
+            TargetArch::riscv32 | TargetArch::riscv64 => ArchFamily::RiscV,
             TargetArch::avr => ArchFamily::Avr,
             TargetArch::csky => ArchFamily::CSky,
             TargetArch::loongarch32 | TargetArch::loongarch64 => ArchFamily::LoongArch,
@@ -418,45 +2275,292 @@ impl<'a> ArchFamily<'a> {
             }
             TargetArch::powerpc | TargetArch::powerpc64 => ArchFamily::PowerPC,
             TargetArch::xtensa => ArchFamily::Xtensa,
-            _ => ArchFamily::Other(target_arch),
+            _ => ArchFamily::Other,
         }
     }
 }
 
 #[track_caller]
-fn assert_diff(tcx: &TesterContext<'_>, expected_path: impl AsRef<Path>, actual: impl AsRef<[u8]>) {
+fn assert_diff(
+    tcx: &TesterContext<'_>,
+    revision: &str,
+    expected_path: impl AsRef<Path>,
+    actual: impl AsRef<[u8]>,
+) {
     let actual = actual.as_ref();
     let expected_path = expected_path.as_ref();
+    if tcx.bless {
+        if fs::read(expected_path).ok().as_deref() != Some(actual) {
+            fs::create_dir_all(expected_path.parent().unwrap()).unwrap();
+            fs::write(expected_path, actual).unwrap();
+            tcx.blessed.set(tcx.blessed.get() + 1);
+        }
+        return;
+    }
     if !expected_path.is_file() {
         fs::create_dir_all(expected_path.parent().unwrap()).unwrap();
         fs::write(expected_path, "").unwrap();
     }
     let expected = fs::read(expected_path).unwrap();
-    if expected != actual {
-        if env::var_os("CI").is_some() {
-            let color = if env::var_os("GITHUB_ACTIONS").is_some() || io::stdout().is_terminal() {
-                &["-c", "color.ui=always"][..]
-            } else {
-                &[]
-            };
-            let mut child = tcx
-                .docker_cmd(&env::current_dir().unwrap(), Some(Stdio::piped()))
-                .into_std()
-                .arg("git")
-                .args(["-c", "core.fsmonitor=false", "--no-pager"])
-                .args(color)
-                .args(["diff", "--no-index", "--"])
-                .arg(expected_path)
-                .arg("-")
-                .spawn()
-                .unwrap();
-            child.stdin.as_mut().unwrap().write_all(actual).unwrap();
-            assert!(!child.wait().unwrap().success());
+    if expected != actual
+        && !(tcx.tester.ignore_whitespace_churn
+            && collapse_spaces(&expected) == collapse_spaces(actual))
+    {
+        if let Some(OnMismatch(on_mismatch)) = &tcx.tester.on_mismatch {
+            on_mismatch(revision, expected_path, actual);
+        }
+        let autowrite =
+            env::var_os("CI").is_none() && !tcx.tester.no_autowrite && !env_set("ASMTEST_NO_WRITE");
+        if tcx.tester.diff_summary && !autowrite {
+            let functions = changed_functions(&expected, actual);
+            tcx.changed.borrow_mut().push((expected_path.into(), functions));
+        } else if env::var_os("CI").is_some() {
+            show_diff(tcx, &env::current_dir().unwrap(), expected_path, actual);
             panic!(
                 "assertion failed; please run test locally and commit resulting changes, or apply the above diff as patch (e.g., `patch -p1 <<'EOF' ... EOF`)"
             );
+        } else if tcx.tester.no_autowrite || env_set("ASMTEST_NO_WRITE") {
+            show_diff(tcx, &env::current_dir().unwrap(), expected_path, actual);
+            panic!(
+                "assertion failed; please review the above diff and run test with `ASMTEST_BLESS=1` to update {}",
+                expected_path.display()
+            );
         } else {
             fs::write(expected_path, actual).unwrap();
         }
     }
 }
+
+/// Returns the names of functions that differ between the given outputs, for [`Tester::diff_summary`].
+fn changed_functions(expected: &[u8], actual: &[u8]) -> Vec<String> {
+    fn functions(s: &str) -> Vec<(&str, &str)> {
+        s.split("\n\n")
+            .filter_map(|func| {
+                // Skip the comment emitted by Tester::function_markers.
+                let name = func.lines().find(|line| !line.starts_with(';'))?.strip_suffix(':')?;
+                Some((name, func))
+            })
+            .collect()
+    }
+    let (expected, actual) = (String::from_utf8_lossy(expected), String::from_utf8_lossy(actual));
+    let (expected, actual) = (functions(&expected), functions(&actual));
+    let mut changed: Vec<String> = vec![];
+    for &(name, func) in actual.iter().chain(&expected) {
+        let same = expected.contains(&(name, func)) && actual.contains(&(name, func));
+        if !same && !changed.iter().any(|n| n == name) {
+            changed.push(name.into());
+        }
+    }
+    changed
+}
+
+/// Returns `true` if the environment variable is set to a non-empty value.
+fn env_set(name: &str) -> bool {
+    env::var_os(name).is_some_and(|v| !v.is_empty())
+}
+
+/// Writes `contents` to `path` only if they differ from the existing contents, to avoid
+/// unnecessary rebuilds of generated crates.
+fn write_if_changed(path: &Path, contents: &str) {
+    if fs::read_to_string(path).ok().as_deref() != Some(contents) {
+        fs::write(path, contents).unwrap();
+    }
+}
+
+/// Shows the diff between the file at `expected_path` and `actual` using the tool set by
+/// [`Tester::diff_tool`].
+///
+/// delta and difftastic are run on the host, and `git diff` is run in the container, which is
+/// also used if the tool on the host cannot be run. `workdir` must contain `expected_path`.
+fn show_diff(tcx: &TesterContext<'_>, workdir: &Path, expected_path: &Path, actual: &[u8]) {
+    // https://no-color.org
+    let no_color = tcx.tester.no_color || env_set("NO_COLOR");
+    let color =
+        !no_color && (env::var_os("GITHUB_ACTIONS").is_some() || io::stdout().is_terminal());
+    // delta always colors the output, so use git diff instead if color is disabled.
+    if tcx.tester.diff_tool != DiffTool::Git
+        && !(no_color && tcx.tester.diff_tool == DiffTool::Delta)
+        && show_diff_on_host(tcx, expected_path, actual, color)
+    {
+        return;
+    }
+    let color = if color {
+        &["-c", "color.ui=always"][..]
+    } else if no_color {
+        &["-c", "color.ui=never"][..]
+    } else {
+        &[]
+    };
+    let mut cmd = tcx.docker_cmd(workdir, Some(Stdio::piped())).into_std();
+    cmd.arg("git")
+        .args(["-c", "core.fsmonitor=false", "--no-pager"])
+        .args(color)
+        .args(["diff", "--no-index", "--"])
+        .arg(expected_path)
+        .arg("-");
+    let mut attempt = 0;
+    loop {
+        let status = cmd.spawn().and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(actual)?;
+            child.wait()
+        });
+        match status {
+            Ok(status) if status.code() != Some(DOCKER_ERROR_EXIT_CODE) => {
+                assert!(!status.success());
+                break;
+            }
+            Ok(_) if attempt < tcx.tester.docker_retries => {
+                attempt += 1;
+                eprintln!(
+                    "failed to show diff; retrying ({attempt}/{})",
+                    tcx.tester.docker_retries
+                );
+                thread::sleep(Duration::from_secs(1 << attempt));
+            }
+            status => panic!("failed to show diff: {status:?}"),
+        }
+    }
+}
+
+/// Shows the diff using the tool installed on the host. Returns `false` if the tool is not available.
+fn show_diff_on_host(
+    tcx: &TesterContext<'_>,
+    expected_path: &Path,
+    actual: &[u8],
+    color: bool,
+) -> bool {
+    let (program, args) = match tcx.tester.diff_tool {
+        DiffTool::Git => unreachable!(),
+        DiffTool::Delta => ("delta", &["--paging=never"][..]),
+        DiffTool::Difft => {
+            ("difft", if color { &["--color=always"][..] } else { &["--color=never"][..] })
+        }
+    };
+    // Unlike git diff, these tools don't read the file from stdin.
+    let dir = tcx.metadata.target_directory.join("tests/asmtest/diff");
+    fs::create_dir_all(&dir).unwrap();
+    let actual_path = dir.join(expected_path.file_name().unwrap());
+    fs::write(&actual_path, actual).unwrap();
+    let mut cmd = cmd!(program);
+    cmd.args(args).arg(expected_path).arg(&actual_path);
+    match cmd.into_std().status() {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("failed to run `{program}` ({e}); falling back to `git diff`");
+            false
+        }
+    }
+}
+
+/// Normalizes instructions of a function for [`Tester::assert_function`].
+fn normalize_function_body(s: &str) -> String {
+    let s = String::from_utf8(collapse_spaces(s.as_bytes())).unwrap();
+    let mut out = String::new();
+    for line in s.lines().map(str::trim_ascii).filter(|line| !line.is_empty()) {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Collapses each run of spaces and tabs outside of string literals into a single space.
+fn collapse_spaces(s: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut in_str = false;
+    let mut prev_space = false;
+    for (i, &b) in s.iter().enumerate() {
+        if !in_str && matches!(b, b' ' | b'\t') {
+            if !prev_space {
+                out.push(b' ');
+            }
+            prev_space = true;
+            continue;
+        }
+        prev_space = false;
+        if b == b'"' && (i == 0 || s[i - 1] != b'\\') {
+            in_str = !in_str;
+        } else if b == b'\n' {
+            in_str = false;
+        }
+        out.push(b);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_codegen_arg() {
+        assert_eq!(parse_codegen_arg("-C opt-level=3"), ["-C", "opt-level=3"]);
+        assert_eq!(parse_codegen_arg("-Copt-level=3"), ["-C", "opt-level=3"]);
+        assert_eq!(parse_codegen_arg(" -Z  merge-functions=disabled "), [
+            "-Z",
+            "merge-functions=disabled"
+        ]);
+        assert_eq!(parse_codegen_arg("-C overflow-checks"), ["-C", "overflow-checks"]);
+        assert_eq!(parse_codegen_arg("-C llvm-args=-x86-asm-syntax=intel"), [
+            "-C",
+            "llvm-args=-x86-asm-syntax=intel"
+        ]);
+    }
+
+    #[test]
+    #[should_panic = "must start with `-C` or `-Z`"]
+    fn test_parse_codegen_arg_no_flag() {
+        parse_codegen_arg("opt-level=3");
+    }
+
+    #[test]
+    #[should_panic = "is not in the form of"]
+    fn test_parse_codegen_arg_bad_key() {
+        parse_codegen_arg("-C opt level=3");
+    }
+
+    #[test]
+    fn test_collapse_spaces() {
+        assert_eq!(collapse_spaces(b"mov  \t eax,\t\tebx\n"), b"mov eax, ebx\n");
+        // Spaces in string literals are kept.
+        assert_eq!(collapse_spaces(b"s  \"a  b\"  c\n"), b"s \"a  b\" c\n");
+        assert_eq!(collapse_spaces(b"s  \"a \\\"  b\"  c\n"), b"s \"a \\\"  b\" c\n");
+        // Unterminated string literals end at the end of the line.
+        assert_eq!(collapse_spaces(b"\"a  b\n c  d\n"), b"\"a  b\n c d\n");
+    }
+
+    #[test]
+    fn test_changed_functions() {
+        let expected = b"a:\n        ret\n\nb:\n        nop\n        ret\n\nc:\n        ret\n";
+        let actual = b"a:\n        ret\n\nb:\n        ret\n\nd:\n        ret\n";
+        assert_eq!(changed_functions(expected, actual), ["b", "d", "c"]);
+        assert!(changed_functions(expected, expected).is_empty());
+        // Comments emitted by Tester::function_markers are skipped.
+        let actual = b"; a\na:\n        ret\n";
+        assert_eq!(changed_functions(b"a:\n        nop\n", actual), ["a"]);
+    }
+
+    #[test]
+    fn test_function_sums() {
+        let out = "; f\nf:\n        ret\n\ng:\n        nop\n        ret\n\ng2:\n        nop\n        ret\n";
+        let sums = function_sums(out);
+        let sums: Vec<_> = sums.lines().map(|line| line.split_once("  ").unwrap()).collect();
+        assert_eq!(sums.iter().map(|&(_, name)| name).collect::<Vec<_>>(), ["f", "g", "g2"]);
+        // The hash only depends on the body.
+        assert_ne!(sums[0].0, sums[1].0);
+        assert_eq!(sums[1].0, sums[2].0);
+        // FNV-1a of "        ret\n", which must be stable across platforms and toolchains.
+        assert_eq!(sums[0].0, "3c8a181c746e57b2");
+    }
+
+    #[test]
+    fn test_combined_report() {
+        let revision = Revision::new("x86_64", "x86_64-unknown-linux-gnu");
+        let out = "g:\n        ret\n\nf:\n        nop\n        ret\n\nh:\n        ret\n\nf:\n        ret\n";
+        let report = combined_report(&Regex::new("^[fg]$").unwrap(), &[(&revision, out.into())]);
+        assert_eq!(
+            report,
+            "; ==== x86_64 (x86_64-unknown-linux-gnu) ====\n\
+             f:\n        nop\n        ret\n\nf:\n        ret\n\ng:\n        ret\n"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Refs:
+// - https://llvm.org/docs/LangRef.html
+
+use alloc::{format, string::String};
+use std::{fs, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::RevisionContext;
+
+pub(crate) fn read(cx: &RevisionContext<'_>) -> String {
+    fs::read_to_string(cx.obj_path.with_extension("ll")).unwrap()
+}
+
+pub(crate) fn handle_ir(cx: &mut RevisionContext<'_>, s: &str) {
+    // Mangled symbol names, optionally quoted.
+    static SYMBOL_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new("([@$])\"?(_ZN[0-9A-Za-z_$.]+E|_R[0-9A-Za-z_]+)\"?").unwrap());
+    // Metadata attachments (e.g., `, !noundef !3`), whose numbering changes with unrelated edits.
+    static METADATA_ATTACHMENT_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(", ![0-9A-Za-z_.]+ ![0-9]+").unwrap());
+    for line in s.lines() {
+        // Module-level metadata (e.g., `!0 = !{i32 7, !"PIC Level", i32 2}`) and the header
+        // lines that contain the name of the codegen unit.
+        if line.starts_with('!')
+            || line.starts_with("; ModuleID = ")
+            || line.starts_with("source_filename = ")
+        {
+            continue;
+        }
+        let line = METADATA_ATTACHMENT_RE.replace_all(line, "");
+        let line = SYMBOL_RE.replace_all(&line, |c: &regex::Captures<'_>| {
+            format!("{}\"{:#}\"", &c[1], rustc_demangle::demangle(&c[2]))
+        });
+        cx.out.push_str(line.trim_ascii_end());
+        cx.out.push('\n');
+    }
+    while cx.out.ends_with("\n\n") {
+        cx.out.pop();
+    }
+}
@@ -9,14 +9,16 @@ use alloc::{
     format,
     string::String,
     vec,
+    vec::Vec,
 };
-use core::cmp;
-use std::{collections::HashMap, sync::LazyLock};
+use core::{cmp, mem};
+use std::{collections::HashMap, eprintln, sync::LazyLock};
 
 use anyhow::Context as _;
+use cargo_config2::cfg::{TargetArch, TargetOs};
 use regex::Regex;
 
-use crate::{ArchFamily, RevisionContext};
+use crate::{ArchFamily, Asm, DOCKER_ERROR_EXIT_CODE, LabelStyle, RevisionContext};
 
 pub(crate) fn disassemble(cx: &mut RevisionContext<'_>) -> String {
     match cx.arch_family {
@@ -35,64 +37,263 @@ pub(crate) fn disassemble(cx: &mut RevisionContext<'_>) -> String {
         ArchFamily::Hexagon => cx.prefer_gnu = false,
         _ => {}
     }
+    if !cx.tcx.tester.objdump_overrides.is_empty() {
+        let target_arch = cx.tcx.config.cfg::<TargetArch, _>(cx.target_name).unwrap();
+        if let Some(&(_, gnu)) =
+            cx.tcx.tester.objdump_overrides.iter().rev().find(|(a, _)| a == target_arch.as_str())
+        {
+            cx.prefer_gnu = gnu;
+        }
+    }
+    let program = cx
+        .revision
+        .config
+        .objdump_program
+        .as_deref()
+        .or(cx.tcx.tester.config.objdump_program.as_deref())
+        .unwrap_or(if cx.prefer_gnu { "objdump" } else { "llvm-objdump" });
     let mut objdump = cx.tcx.docker_cmd(cx.obj_path.parent().unwrap(), None);
-    objdump.args([
-        if cx.prefer_gnu { "objdump" } else { "llvm-objdump" },
-        "-Cd",
-        "--disassembler-color=off",
-    ]);
+    objdump.args([program, "-Cd", "--disassembler-color=off"]);
     objdump.arg(&cx.obj_path);
+    if cx.tcx.tester.resolve_relocations {
+        objdump.arg("-r");
+    }
+    if cx.tcx.tester.visualize_jumps && !cx.prefer_gnu {
+        objdump.arg("--visualize-jumps=ascii");
+    }
+    if !cx.revision.disasm_features.is_empty() {
+        if program.contains("llvm") {
+            objdump.arg(format!("--mattr={}", cx.revision.disasm_features.join(",")));
+        } else {
+            eprintln!(
+                "warning: ignoring disassembler features {:?} for revision {}; they are only supported by llvm-objdump",
+                cx.revision.disasm_features, cx.revision.name
+            );
+        }
+    }
     match cx.arch_family {
         ArchFamily::Mips => {
             // TODO(mips)
             objdump.args(["-M", "reg-names=numeric"]);
+            // Release 6 reassigned the encodings of some pre-r6 instructions, so make sure
+            // they are decoded as r6 instructions. microMIPS code is detected by objdump
+            // from the symbol table, so no hint is needed for it.
+            match cx.tcx.config.cfg::<TargetArch, _>(cx.target_name).unwrap() {
+                TargetArch::mips32r6 => {
+                    objdump.args(["-m", "mips:isa32r6"]);
+                }
+                TargetArch::mips64r6 => {
+                    objdump.args(["-m", "mips:isa64r6"]);
+                }
+                _ => {}
+            }
+        }
+        ArchFamily::Sparc => {
+            // 64-bit objects are always decoded as V9, but 32-bit objects are decoded as V8
+            // unless the ELF header says otherwise, and then V9 instructions used by V8+ code
+            // (e.g., ldx, casx) are not decoded. So select the variant explicitly.
+            let target_features = &cx.revision.target_features;
+            let v8plus = cx.tcx.config.cfg::<TargetArch, _>(cx.target_name).unwrap()
+                == TargetArch::sparc
+                && !target_features.iter().any(|f| f == "-v8plus")
+                && (target_features.iter().any(|f| f == "+v8plus" || f == "+v9")
+                    // Linux targets use V8+ by default.
+                    || cx.tcx.config.cfg::<TargetOs, _>(cx.target_name).unwrap() == TargetOs::linux
+                    || cx.revision.target_cpu.as_deref().is_some_and(|cpu| {
+                        cpu == "v9" || cpu.starts_with("ultrasparc") || cpu.starts_with("niagara")
+                    }));
+            if v8plus {
+                objdump.args(["-m", "sparc:v8plus"]);
+            }
         }
         ArchFamily::X86 => {
-            if cx.tcx.tester.config.att_syntax || cx.revision.config.att_syntax {
+            if cx.enabled(|c| c.att_syntax) {
                 objdump.args(["-M", "att"]);
             } else {
                 objdump.args(["-M", "intel"]);
             }
         }
+        ArchFamily::RiscV => {
+            if cx.enabled(|c| c.riscv_no_aliases) {
+                objdump.args(["-M", "no-aliases"]);
+            }
+            if cx.enabled(|c| c.riscv_numeric_reg_names) {
+                objdump.args(["-M", "numeric"]);
+            }
+        }
         _ => {}
     }
+    if let Some(name) = &cx.revision.only_symbol {
+        // objdump selects symbols by their demangled names if -C is passed.
+        let symbols = resolve_symbol(cx, program, name);
+        if program.contains("llvm") {
+            if let Some(s) = symbols.iter().find(|s| s.contains(',')) {
+                panic!(
+                    "symbol `{s}` cannot be selected with llvm-objdump because its name contains commas (revision {})",
+                    cx.revision.name
+                );
+            }
+            objdump.arg(format!("--disassemble-symbols={}", symbols.join(",")));
+        } else {
+            if symbols.len() > 1 {
+                panic!(
+                    "`{name}` matches multiple symbols, but GNU objdump can select only one: {symbols:?} (revision {})",
+                    cx.revision.name
+                );
+            }
+            objdump.arg(format!("--disassemble={}", symbols[0]));
+        }
+    }
+    if let Some((start, stop)) = cx.revision.address_range {
+        objdump.arg(format!("--start-address={start:#x}"));
+        objdump.arg(format!("--stop-address={stop:#x}"));
+    }
     objdump.args(&cx.tcx.tester.config.objdump_args);
     objdump.args(&cx.revision.config.objdump_args);
-    objdump.read().unwrap()
+    objdump.deadline(cx.deadline);
+    let res = objdump.read_with_retry(cx.tcx.tester.docker_retries, &[DOCKER_ERROR_EXIT_CODE]);
+    cx.check_timeout(&res);
+    res.unwrap()
 }
 
 pub(crate) fn handle_asm<'a>(cx: &mut RevisionContext<'a>, s: &'a str) {
-    static FUNC_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new("\\n[0-9]{8}[0-9]* <").unwrap());
+    static FUNC_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new("\\n[0-9a-f]{8}[0-9a-f]* <").unwrap());
+    // Compiler-internal labels for constants (e.g., `.LCPI0_0`, `.Lanon.<hash>.0`), whose
+    // names change with unrelated edits.
+    static CONST_LABEL_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new("<(\\.LCPI[0-9]+_[0-9]+|(\\.L|l_)?anon\\.[0-9a-f]+\\.[0-9]+)(\\+0x[0-9a-f]+)?>")
+            .unwrap()
+    });
+    // Comments added by llvm-objdump to immediates of Arm instructions (e.g., `bl 0x8 <f> @ imm = #0x4`).
+    // They are aligned with spaces, so the number of spaces before them varies.
+    static IMM_ANNOTATION_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(" +@ imm = #(-)?0x[0-9a-f]+").unwrap());
+    // Hexadecimal numbers with `0x` prefix, and addresses without `0x` prefix that are
+    // followed by the symbol they point to (e.g., `e <f+0xe>` in GNU objdump's output).
+    // Word boundaries are ASCII-only (`(?-u:\b)`) because the `unicode-perl` feature of
+    // regex is disabled.
+    static HEX_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            "(?-u:\\b)0[xX](?P<hex>[0-9a-fA-F]+)(?-u:\\b)|(?P<lead>^|[ \t,(])(?P<addr>[0-9a-fA-F]+) <",
+        )
+        .unwrap()
+    });
+    // Register names to canonicalize. Symbol references (`<..>`) are matched first to leave
+    // them as is. Word boundaries are ASCII-only for the same reason as HEX_RE.
+    static ARM_REG_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new("<[^>]*>|(?-u:\\b)(?P<reg>sb|sl|fp|ip)(?-u:\\b)").unwrap());
+    static MIPS_REG_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new("<[^>]*>|\\$(?P<reg>zero|at|k0|k1|gp|sp|fp|s8|ra)(?-u:\\b)").unwrap()
+    });
+    // Arm mapping symbols (`$a`, `$t`, and `$d`, optionally followed by `.<n>`) mark the start of
+    // Arm code, Thumb code, and data in the section. They are not functions, so their contents are
+    // treated as part of the preceding function.
+    static ARM_MAPPING_SYMBOL_RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new("^\\$[atd](\\.[0-9]+)?>:").unwrap());
+    // ELF relocations shown by `-r` after the instruction they apply to:
+    //   <\t\t>0000000000000005:  R_X86_64_PC32<\t>.text._ZN7closure4call17h27d20dd906cbe2ccE-0x4
+    //   <\t\t\t>5: R_X86_64_PC32<\t>.text._ZN7closure4call17h27d20dd906cbe2ccE-0x4
+    // Relocations without symbol (e.g., `R_RISCV_RELAX`, shown with `*ABS*`) are ignored.
+    static RELOC_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            "^\\t+(?P<offset>[0-9a-f]+): +(?P<kind>R_[0-9A-Z_a-z]+)\\t(?P<symbol>.+?)(?P<addend>(\\+|-)0x[0-9a-f]+)?$",
+        )
+        .unwrap()
+    });
+    let strip_imm_annotations =
+        cx.tcx.tester.strip_imm_annotations && cx.arch_family == ArchFamily::Arm;
+    let canonicalize_hex = cx.tcx.tester.canonicalize_hex;
+    let strip_cet = cx.tcx.tester.strip_cet && cx.arch_family == ArchFamily::X86;
+    let resolve_relocations = cx.tcx.tester.resolve_relocations;
+    let reg_re = match cx.arch_family {
+        _ if !cx.tcx.tester.canonicalize_registers => None,
+        ArchFamily::Arm => Some(&*ARM_REG_RE),
+        ArchFamily::Mips => Some(&*MIPS_REG_RE),
+        _ => None,
+    };
     let mut label_map = HashMap::new();
+    let mut const_labels = vec![];
     let mut lines = vec![];
-    let mut func_iter = FUNC_RE.split(s);
-    func_iter.next();
-    for s in func_iter {
+    let mut relocs = vec![];
+    let mut relocated = vec![];
+    let funcs: Vec<_> = FUNC_RE
+        .find_iter(s)
+        .filter(|m| {
+            cx.arch_family != ArchFamily::Arm || !ARM_MAPPING_SYMBOL_RE.is_match(&s[m.end()..])
+        })
+        .collect();
+    // Collect names with hash in advance to shorten references to them in each function, instead
+    // of rewriting the whole output at the end, which temporarily doubles the memory usage.
+    let mut verbose_function_names = String::new();
+    if !cx.prefer_gnu {
+        for m in &funcs {
+            let header = s[m.end()..].lines().next().unwrap_or_default();
+            let Some(raw_verbose_function_name) = header.strip_suffix(">:") else { continue };
+            if strip_hash(raw_verbose_function_name).is_some() {
+                if !verbose_function_names.is_empty() {
+                    verbose_function_names.push('|');
+                }
+                verbose_function_names.push_str(&regex::escape(raw_verbose_function_name));
+            }
+        }
+    }
+    let verbose_function_name_re =
+        (!verbose_function_names.is_empty()).then(|| Regex::new(&verbose_function_names).unwrap());
+    let include_file_comments = cx.tcx.tester.include_file_comments;
+    if include_file_comments {
+        //   /path/to/foo.o:	file format elf64-x86-64
+        if let Some((_, format)) = s.lines().find_map(|line| line.split_once("file format ")) {
+            cx.out.push_str("; file format ");
+            cx.out.push_str(format.trim_ascii());
+            cx.out.push_str("\n\n");
+        }
+    }
+    // If functions are selected by Revision::only_symbol, emit them in the order of their names
+    // instead of the order of symbols in the object, which may change between builds. Names are
+    // compared without the hash, which changes with unrelated changes to the crate, and
+    // functions with the same name (e.g., instances of a generic function with the legacy
+    // mangling) are ordered by their contents.
+    let mut order: Vec<usize> = (0..funcs.len()).collect();
+    if cx.revision.only_symbol.is_some() {
+        order.sort_by_key(|&i| {
+            let func = &s[funcs[i].end()..funcs.get(i + 1).map_or(s.len(), regex::Match::start)];
+            let (header, body) = func.split_once('\n').unwrap_or((func, ""));
+            let name = header.strip_suffix(">:").unwrap_or(header);
+            (strip_hash(name).unwrap_or(name), body)
+        });
+    }
+    let mut last_section = None;
+    for i in order {
+        let m = &funcs[i];
+        let own_section = section_before(s, m.start());
+        let section = if include_file_comments { own_section } else { None };
+        // Header lines of the skipped mapping symbols are ignored as non-instruction lines.
+        let s = &s[m.end()..funcs.get(i + 1).map_or(s.len(), regex::Match::start)];
+        // Usually 0 for relocatable objects, but may be non-zero for linked artifacts.
+        let func_addr = u64::from_str_radix(m.as_str()[1..].trim_end_matches(" <"), 16).unwrap();
         let mut label_count = 0;
         label_map.clear();
+        const_labels.clear();
         lines.clear();
-        let (raw_verbose_function_name, s) =
-            s.split_once(">:\n").with_context(|| s.to_owned()).unwrap();
+        relocs.clear();
+        relocated.clear();
+        // The header is the rest of the line. Splitting at the end of the line instead of
+        // searching `>:` keeps names containing it (e.g., in generic arguments) intact.
+        //   0000000000000000 <<foo::S<u8> as foo::Tr>::f>:
+        //                     ^^^^^^^^^^^^^^^^^^^^^^^^^^^-- raw_verbose_function_name
+        // There is no newline after the header if the symbol has no instructions (e.g., a label
+        // in global_asm) at the end of the output.
+        let (header, s) = s.split_once('\n').unwrap_or((s, ""));
+        let raw_verbose_function_name =
+            header.strip_suffix(">:").with_context(|| header.to_owned()).unwrap();
         let mut function_name = Cow::Borrowed(raw_verbose_function_name);
         let verbose_function_name = regex::escape(raw_verbose_function_name);
         if !cx.prefer_gnu {
-            if let Some((name, hash)) = raw_verbose_function_name.rsplit_once("::") {
-                // <path::to::fn::h[0-9a-f]{16}>:
-                if hash.len() == 17
-                    && hash.as_bytes()[0] == b'h'
-                    && hash.as_bytes()[1..]
-                        .iter()
-                        .all(|&b| b.is_ascii_digit() | matches!(b, b'a'..=b'f'))
-                {
-                    cx.verbose_function_names.push(verbose_function_name.clone());
-                    function_name = Cow::Borrowed(name);
-                }
+            if let Some(name) = strip_hash(raw_verbose_function_name) {
+                function_name = Cow::Borrowed(name);
             }
         }
-        // TODO(hexagon,msp430,etc.): multiple spaces
-        // https://github.com/taiki-e/atomic-maybe-uninit/blob/5e1cd2165c45e4362c6638b06b24fc37ea79884a/tests/asm-test/asm/atomic-maybe-uninit/hexagon.asm#L2346
-        // https://github.com/taiki-e/atomic-maybe-uninit/blob/5e1cd2165c45e4362c6638b06b24fc37ea79884a/tests/asm-test/asm/atomic-maybe-uninit/msp430.asm#L2
-        //
         // TODO(sparc): constant display bug:
         // https://github.com/taiki-e/atomic-maybe-uninit/blob/5e1cd2165c45e4362c6638b06b24fc37ea79884a/tests/asm-test/asm/atomic-maybe-uninit/sparcv8_leoncasa.asm#L1463
         // https://github.com/taiki-e/atomic-maybe-uninit/blob/5e1cd2165c45e4362c6638b06b24fc37ea79884a/tests/asm-test/asm/atomic-maybe-uninit/sparc64.asm#L564
@@ -104,11 +305,14 @@ pub(crate) fn handle_asm<'a>(cx: &mut RevisionContext<'a>, s: &'a str) {
         //
         //         move_!($cc, "1", "{r}"),                    // if cc.Z { r = 1 }
         //         ?
-        if cx.is_powerpc64be {
+        if cx.big_endian {
             if let Some(mut name) = function_name.strip_prefix(".text.") {
                 name = name.strip_prefix("unlikely.").unwrap_or(name);
-                // .text on big-endian PowerPC64 is not demangled by objdump 2.45.
-                function_name = Cow::Owned(format!("{:#}", rustc_demangle::demangle(name)));
+                // Functions named by their section symbols (`.text.<mangled name>`) are not
+                // demangled by objdump 2.45. This is known to happen on big-endian PowerPC64,
+                // but is handled on all big-endian targets since the names are otherwise
+                // unreadable.
+                function_name = Cow::Owned(demangle(name).into_owned());
             }
         }
         if cx.arch_family == ArchFamily::Xtensa {
@@ -137,43 +341,69 @@ pub(crate) fn handle_asm<'a>(cx: &mut RevisionContext<'a>, s: &'a str) {
             //           l32r              a9, .literal.asm_test::compare_exchange_weak::u16::seqcst_acquire
             if let Some(name) = function_name.strip_prefix(".literal.") {
                 // .literal is not demangled by objdump 2.45.
-                function_name =
-                    Cow::Owned(format!(".literal.{:#}", rustc_demangle::demangle(name)));
+                function_name = Cow::Owned(format!(".literal.{}", demangle(name)));
             }
         }
-        let (label_re, addr_pos) = match cx.arch_family {
-            ArchFamily::Arm if !cx.prefer_gnu => (
-                format!(
-                    "(-)?(0x)?[0-9a-f]+ <{verbose_function_name}(\\+0x([0-9a-f]+))?>( @ imm = #(-)?0x[0-9a-f]+)?"
-                ),
-                4,
-            ),
-            ArchFamily::Avr => (
-                format!(
-                    "\\.(\\+|-)[0-9]+ +\t; 0x([0-9a-f]+) <(__zero_reg__|{verbose_function_name})(\\+0x[0-9a-f]+)?>"
-                ),
-                2,
-            ),
-            ArchFamily::CSky => (
-                format!(
-                    "0x[0-9a-f]+\t// (0x)?[0-9a-f]+ <{verbose_function_name}(\\+0x([0-9a-f]+))?>"
-                ),
-                3,
-            ),
-            ArchFamily::LoongArch if cx.prefer_gnu => (
-                format!(
-                    "(-)?(0x)?[0-9a-f]+\t# (-)?(0x)?[0-9a-f]+ <{verbose_function_name}(\\+0x([0-9a-f]+))?>"
-                ),
-                6,
-            ),
-            ArchFamily::Msp430 => ("\\$(\\+|-)[0-9]+ +\t;abs 0x([0-9a-f]+)".to_owned(), 2),
-            _ => (format!("(-)?(0x)?[0-9a-f]+ <{verbose_function_name}(\\+0x([0-9a-f]+))?>"), 4),
-        };
-        let label_re = Regex::new(&label_re).unwrap();
-        for c in label_re.captures_iter(s) {
-            let addr = c.get(addr_pos).map_or("0", |m| m.as_str());
-            let addr = u64::from_str_radix(addr, 16).with_context(|| addr.to_owned()).unwrap();
-            label_map.insert(addr, None);
+        let label_re =
+            Regex::new(&label_pattern(cx).replace("{f}", &verbose_function_name)).unwrap();
+        // The targets of relocated instructions are placeholders (e.g., the next instruction
+        // for x86 calls, or the instruction itself for AArch64 `bl`), so they don't get labels.
+        // Relocations are applied to the last instruction line at or before their offset,
+        // because GNU objdump shows them after the rest of the bytes of long instructions.
+        let mut targets = vec![];
+        let mut line_addrs = vec![];
+        for line in s.lines() {
+            if let Some(c) = RELOC_RE.captures(line).filter(|_| resolve_relocations) {
+                let offset = u64::from_str_radix(&c["offset"], 16).unwrap();
+                if &c["symbol"] != "*ABS*" {
+                    let addend = c.name("addend").map_or(0, |m| {
+                        let (sign, n) = m.as_str().split_at(1);
+                        let n = i64::from_str_radix(&n[2..], 16).unwrap();
+                        if sign == "-" { -n } else { n }
+                    });
+                    let kind = c.name("kind").unwrap().as_str();
+                    let symbol = c.name("symbol").unwrap().as_str();
+                    // Relocations against the function itself (e.g., branches of AVR, whose
+                    // assembler leaves them to the linker for relaxation) target local labels.
+                    // This is not the case for x86-64 PC-relative relocations, whose addends
+                    // depend on the length of the instruction.
+                    let local = if x86_64_pc_relative(kind) {
+                        None
+                    } else if Some(symbol) == own_section {
+                        Some(addend.cast_unsigned())
+                    } else if symbol == raw_verbose_function_name {
+                        Some(func_addr.wrapping_add_signed(addend))
+                    } else {
+                        None
+                    };
+                    if let Some(target) = local {
+                        label_map.insert(target, None);
+                    }
+                    relocs.push(Reloc { offset, inst: 0, kind, symbol, addend, local });
+                }
+                if let Some(i) = line_addrs.partition_point(|&a| a <= offset).checked_sub(1) {
+                    relocated.push(line_addrs[i]);
+                }
+            } else if line.starts_with(' ') {
+                let Some(addr) = line
+                    .trim_ascii_start()
+                    .split_once(':')
+                    .and_then(|(addr, _)| u64::from_str_radix(addr, 16).ok())
+                else {
+                    continue;
+                };
+                line_addrs.push(addr);
+                for c in label_re.captures_iter(line) {
+                    if let Some(target) = label_addr(&c, func_addr, addr) {
+                        targets.push((addr, target));
+                    }
+                }
+            }
+        }
+        for (addr, target) in targets {
+            if !relocated.contains(&addr) {
+                label_map.insert(target, None);
+            }
         }
         let mut line_iter = s.lines().peekable();
         while let Some(&s) = line_iter.peek() {
@@ -181,6 +411,10 @@ pub(crate) fn handle_asm<'a>(cx: &mut RevisionContext<'a>, s: &'a str) {
                 line_iter.next();
                 continue;
             }
+            // Lines other than instructions start without spaces, and are ignored. This includes
+            // line number and inlining annotations added by `-l` (`; /path/to/lib.rs:15`,
+            // `core::sync::atomic::atomic_load:`), which keeps the output independent of debuginfo,
+            // and relocations, which are collected above.
             if s.starts_with(' ') {
                 //  0: 89 f0                        <\t>mov	eax, esi
                 // ^-- trim_ascii_start
@@ -189,42 +423,94 @@ pub(crate) fn handle_asm<'a>(cx: &mut RevisionContext<'a>, s: &'a str) {
                 //                                  ^^^^-- split_once('\t')
                 //          ^^^^^^^^^^^^^^^^^^^^^^^^-- trim_ascii_start
                 //                                         ^-- split_once(['\t', ' '])
-                if let Some((addr, s)) = s.trim_ascii_start().split_once(':') {
-                    let addr =
-                        u64::from_str_radix(addr, 16).with_context(|| addr.to_owned()).unwrap();
+                let addr = s
+                    .trim_ascii_start()
+                    .split_once(':')
+                    .and_then(|(addr, s)| Some((u64::from_str_radix(addr, 16).ok()?, s)));
+                if let Some((addr, s)) = addr {
                     if let Some(n) = label_map.get_mut(&addr) {
                         *n = Some(label_count);
                         lines.push(Line::Label { num: label_count });
                         label_count += 1;
                     }
                     let s = s.trim_ascii_start();
-                    let Some((_raw_insn, mut s)) = s.split_once('\t') else {
-                        assert_eq!(cx.arch_family, ArchFamily::Msp430);
-                        for n in s.split([' ', '\t']) {
-                            assert!(
-                                n.is_empty()
-                                    || n.len() == 2
-                                        && n.as_bytes().iter().all(u8::is_ascii_hexdigit)
-                            );
+                    let Some((raw_insn, mut s)) = s.split_once('\t') else {
+                        // No tab between raw bytes and instruction. This is either the rest of
+                        // the bytes of the previous long instruction (e.g., GNU objdump for
+                        // MSP430), or an instruction separated from the bytes by spaces.
+                        //
+                        //    4: 20 01
+                        //       ^^^^^-- raw bytes
+                        //    4: 20 01     mov r4, r5
+                        //       ^^^^^-- raw bytes (only if followed by two or more spaces)
+                        //
+                        // A line is only treated as bytes if all of its words are hex bytes,
+                        // so that a mnemonic that looks like a hex byte (e.g., `bc`) is not
+                        // mistaken for bytes.
+                        let s = s.trim_ascii();
+                        let is_byte = |n: &str| {
+                            n.len() == 2 && n.as_bytes().iter().all(u8::is_ascii_hexdigit)
+                        };
+                        let (raw_insn, s) = if s.split_ascii_whitespace().all(is_byte) {
+                            (s, "")
+                        } else {
+                            match s.split_once("  ") {
+                                Some((raw_insn, rest)) if raw_insn.split(' ').all(is_byte) => {
+                                    (raw_insn, rest.trim_ascii_start())
+                                }
+                                _ => ("", s),
+                            }
+                        };
+                        if s.is_empty() {
+                            // Remaining bytes of the previous instruction.
+                            if let Some(Line::Inst { bytes, .. }) = lines.last_mut() {
+                                if !raw_insn.is_empty() {
+                                    let bytes = bytes.to_mut();
+                                    bytes.push(' ');
+                                    bytes.push_str(raw_insn);
+                                }
+                            }
+                        } else {
+                            let (inst, operands) = s.split_once(' ').unwrap_or((s, ""));
+                            lines.push(Line::Inst {
+                                addr,
+                                name: inst,
+                                operands: operands.trim_ascii().into(),
+                                bytes: raw_insn.into(),
+                                jumps: "",
+                            });
                         }
                         line_iter.next();
                         continue;
                     };
+                    let raw_insn = raw_insn.trim_ascii();
                     if cx.arch_family == ArchFamily::Hexagon {
                         //    8:<\t>e4 5f 00 78<\t>78005fe4   <\t>r4 = #0xff
                         //    8:<\t>e4 5f 00 78<\t>78005fe4 { <\t>r4 = #0xff
                         //                     ^^^^-- trim_ascii_start
                         //                                 ^-- split_once(' ')
                         //                                    ^^^^-- split_once('\t')
-                        s = s.trim_ascii_start().split_once(' ').unwrap().1;
-                        let (pre, s) = s.split_once('\t').unwrap();
+                        // Packets have several line shapes (e.g., a line only with a loop end
+                        // marker), so lines that don't match the above are passed through as is.
+                        s = s.trim_ascii_start().split_once([' ', '\t']).map_or("", |(_, s)| s);
+                        let (pre, s) = s.split_once('\t').unwrap_or(("", s));
                         let pre = pre.trim_ascii();
                         let s = s.trim_ascii();
-                        if let Some((operands, s)) = s.split_once(';') {
+                        if pre.is_empty() && s.is_empty() {
+                            // A line only with the encoding; keep its bytes with the previous
+                            // instruction.
+                            if let Some(Line::Inst { bytes, .. }) = lines.last_mut() {
+                                let bytes = bytes.to_mut();
+                                bytes.push(' ');
+                                bytes.push_str(raw_insn);
+                            }
+                        } else if let Some((operands, s)) = s.split_once(';') {
                             lines.push(Line::Inst {
                                 addr,
                                 name: pre,
                                 operands: operands.trim_ascii_end().into(),
+                                bytes: raw_insn.into(),
+                                jumps: "",
                             });
                             let mut s = s.trim_ascii_start();
                             while let Some((operands, next)) = s.split_once(';') {
@@ -232,6 +518,8 @@ pub(crate) fn handle_asm<'a>(cx: &mut RevisionContext<'a>, s: &'a str) {
                                     addr, // TODO
                                     name: "",
                                     operands: operands.trim_ascii_end().into(),
+                                    bytes: "".into(),
+                                    jumps: "",
                                 });
                                 s = next.trim_ascii_start();
                             }
@@ -239,66 +527,572 @@ pub(crate) fn handle_asm<'a>(cx: &mut RevisionContext<'a>, s: &'a str) {
                                 addr, // TODO
                                 name: "",
                                 operands: s.into(),
+                                bytes: "".into(),
+                                jumps: "",
                             });
                         } else {
-                            lines.push(Line::Inst { addr, name: pre, operands: s.into() });
+                            lines.push(Line::Inst {
+                                addr,
+                                name: pre,
+                                operands: s.into(),
+                                bytes: raw_insn.into(),
+                                jumps: "",
+                            });
                         }
                     } else {
+                        let mut jumps = "";
+                        if cx.tcx.tester.visualize_jumps {
+                            //    4: 74 05<\t>  +-- <\t>je	0xb <foo+0xb>
+                            //                   ^^^^^^-- split_once('\t')
+                            if let Some((j, rest)) = s.split_once('\t') {
+                                if !j.contains(|c: char| c.is_ascii_alphanumeric()) {
+                                    jumps = j;
+                                    s = rest;
+                                }
+                            }
+                        }
                         let (inst, operands) =
                             s.trim_ascii_start().split_once(['\t', ' ']).unwrap_or((s, ""));
                         lines.push(Line::Inst {
                             addr,
                             name: inst.trim_ascii_end(),
                             operands: operands.trim_ascii().into(),
+                            bytes: raw_insn.into(),
+                            jumps,
                         });
                     }
                     line_iter.next();
                     continue;
                 }
+                // Lines without address that are indented with tabs like the instruction
+                // column are continuations of the operands of the previous instruction wrapped
+                // by the disassembler (e.g., long lists of immediates). Other lines without
+                // address are ignored, since they may be anything printed as is, such as
+                // source lines added by `-S`.
+                let text = s.trim_ascii_start();
+                let indent = &s[..s.len() - text.len()];
+                if let Some(Line::Inst { operands, .. }) =
+                    lines.last_mut().filter(|_| indent.contains('\t'))
+                {
+                    let s = text.trim_ascii_end();
+                    if !s.is_empty() {
+                        let o = operands.to_mut();
+                        if !o.is_empty() {
+                            o.push(' ');
+                        }
+                        o.push_str(s);
+                    }
+                }
             }
             line_iter.next();
         }
-        for line in &mut lines {
-            let Line::Inst { addr: inst_addr, operands, .. } = line else { continue };
-            let Cow::Borrowed(s) = *operands else { unreachable!() };
-            *operands = label_re.replace_all(s, |c: &regex::Captures<'_>| {
-                let addr = c.get(addr_pos).map_or("0", |m| m.as_str());
-                let addr = u64::from_str_radix(addr, 16).with_context(|| addr.to_owned()).unwrap();
-                if let Some(num) = label_map[&addr] {
-                    if *inst_addr > addr { format!("{num}b") } else { format!("{num}f") }
-                } else {
-                    c.get(0).unwrap().as_str().to_owned()
+        if let Some((start, _)) = cx.revision.address_range {
+            // GNU objdump shows the header as `<f+0x..>:`, and llvm-objdump shows the header
+            // of the function but starts instructions at the start address.
+            let first_addr = lines.iter().find_map(|line| {
+                if let Line::Inst { addr, .. } = *line { Some(addr) } else { None }
+            });
+            if raw_verbose_function_name.contains("+0x")
+                || first_addr.is_some_and(|a| a != func_addr)
+            {
+                panic!(
+                    "start address {start:#x} is in the middle of function {function_name} (revision {}); \
+                     label resolution requires disassembling from the start of functions",
+                    cx.revision.name
+                );
+            }
+        }
+        if strip_cet {
+            lines.retain_mut(|line| {
+                let Line::Inst { name, operands, .. } = line else { return true };
+                match *name {
+                    "endbr64" | "endbr32" => false,
+                    // The prefix is printed either as a separate instruction or with the
+                    // prefixed branch in the operands (e.g., `notrack jmp rcx`).
+                    "notrack" | "bnd" => match operands {
+                        Cow::Borrowed("") => false,
+                        Cow::Borrowed(o) => {
+                            let (inst, o) = o.split_once(['\t', ' ']).unwrap_or((o, ""));
+                            *name = inst;
+                            *operands = Cow::Borrowed(o.trim_ascii_start());
+                            true
+                        }
+                        Cow::Owned(_) => true,
+                    },
+                    _ => true,
                 }
             });
         }
-        write_func(cx, &function_name, &lines);
-    }
-    if !cx.verbose_function_names.is_empty() {
-        let mut re = String::new();
-        for verbose_function_name in &cx.verbose_function_names {
-            if !re.is_empty() {
-                re.push('|');
+        // The index of the function in the output, used by LabelStyle::Dotted.
+        let func_index = cx.function_names.len();
+        let label_style = cx.tcx.tester.label_style;
+        let label = |num: u32, inst_addr: u64, addr: u64| match label_style {
+            LabelStyle::Numeric => {
+                if inst_addr > addr {
+                    format!("{num}b")
+                } else {
+                    format!("{num}f")
+                }
+            }
+            LabelStyle::Dotted => format!(".LBB{func_index}_{num}"),
+        };
+        // Labels omitted by Tester::head_instructions are not shown, so branches to them are
+        // left as is.
+        let label_limit = match cx.tcx.tester.head_instructions {
+            Some(n) => lines[head_len(&lines, n)..]
+                .iter()
+                .find_map(|line| match *line {
+                    Line::Label { num } => Some(num),
+                    Line::Inst { .. } => None,
+                })
+                .unwrap_or(u32::MAX),
+            None => u32::MAX,
+        };
+        // Self-references (`<f+0x..>`) left after label resolution indicate that a local label
+        // was not created for them, unless they are left intentionally.
+        let last_addr = lines
+            .iter()
+            .rev()
+            .find_map(|line| if let Line::Inst { addr, .. } = *line { Some(addr) } else { None })
+            .unwrap_or(func_addr);
+        let self_refs = cx.tcx.tester.strict_labels.then(|| {
+            [format!("<{raw_verbose_function_name}+0x"), format!("<{raw_verbose_function_name}>")]
+        });
+        let inst_addrs: Vec<u64> = lines
+            .iter()
+            .filter_map(|line| if let Line::Inst { addr, .. } = *line { Some(addr) } else { None })
+            .collect();
+        for r in &mut relocs {
+            r.inst = inst_addrs
+                .get(inst_addrs.partition_point(|&a| a <= r.offset).saturating_sub(1))
+                .copied()
+                .unwrap_or(func_addr);
+        }
+        let mut prev_addr = None;
+        for line in &mut lines {
+            let Line::Inst { addr: inst_addr, operands, bytes, .. } = line else { continue };
+            let s = mem::take(operands);
+            let mut kept = false;
+            // Hexagon packets may have several instructions at the same address.
+            let first = prev_addr != Some(*inst_addr);
+            prev_addr = Some(*inst_addr);
+            let mut reloc_targets =
+                relocs.iter().filter(|r| first && r.inst == *inst_addr).map(|r| {
+                    if let Some(Some(num)) = r.local.and_then(|target| label_map.get(&target)) {
+                        if *num < label_limit {
+                            return label(*num, *inst_addr, r.local.unwrap());
+                        }
+                    }
+                    // The addend is adjusted to show the target the instruction refers to.
+                    let addend = if x86_64_pc_relative(r.kind) {
+                        let inst_end = *inst_addr + bytes.split_ascii_whitespace().count() as u64;
+                        r.addend.wrapping_add_unsigned(inst_end.wrapping_sub(r.offset))
+                    } else {
+                        r.addend
+                    };
+                    let symbol = reloc_symbol(r.symbol);
+                    match addend {
+                        0 => format!("<{symbol}>"),
+                        1.. => format!("<{symbol}+{addend:#x}>"),
+                        _ => format!("<{symbol}-{:#x}>", addend.unsigned_abs()),
+                    }
+                });
+            let new = if let Some(target) = reloc_targets.next() {
+                // Replace the placeholder reference with the target of the relocation, or
+                // append it if there is no reference (e.g., AArch64 `add x8, x8, #0x0` with
+                // `R_AARCH64_ADD_ABS_LO12_NC`).
+                let mut new = match label_re.find(&s) {
+                    Some(m) => format!("{}{target}{}", &s[..m.start()], &s[m.end()..]),
+                    None if s.is_empty() => target,
+                    None => format!("{s} {target}"),
+                };
+                for target in reloc_targets {
+                    new.push(' ');
+                    new.push_str(&target);
+                }
+                Cow::Owned(new)
+            } else {
+                label_re.replace_all(&s, |c: &regex::Captures<'_>| {
+                    let Some(addr) = label_addr(c, func_addr, *inst_addr) else {
+                        kept = true;
+                        return c.get(0).unwrap().as_str().to_owned();
+                    };
+                    match label_map[&addr] {
+                        Some(num) if num < label_limit => label(num, *inst_addr, addr),
+                        label => {
+                            // Targets outside of the instructions (e.g., the end of the function
+                            // referenced by unrelocated tail calls, or outside of the range passed
+                            // by Revision::address_range) cannot be resolved.
+                            kept |= label.is_some()
+                                || cx.revision.address_range.is_some()
+                                || addr > last_addr;
+                            c.get(0).unwrap().as_str().to_owned()
+                        }
+                    }
+                })
+            };
+            *operands = match new {
+                Cow::Owned(new) => Cow::Owned(new),
+                Cow::Borrowed(_) => s,
+            };
+            if let Some(self_refs) = &self_refs {
+                if !kept && self_refs.iter().any(|r| operands.contains(&**r)) {
+                    panic!(
+                        "unresolved reference to `{function_name}` itself found at {inst_addr:#x}: `{operands}` (revision {})",
+                        cx.revision.name
+                    );
+                }
+            }
+            if strip_imm_annotations {
+                if let Cow::Owned(new) = IMM_ANNOTATION_RE.replace_all(operands, "") {
+                    *operands = Cow::Owned(new);
+                }
+            }
+            if CONST_LABEL_RE.is_match(operands) {
+                let new = CONST_LABEL_RE.replace_all(operands, |c: &regex::Captures<'_>| {
+                    let name = c.get(1).unwrap().as_str();
+                    let num = const_labels.iter().position(|l| l == name).unwrap_or_else(|| {
+                        const_labels.push(name.to_owned());
+                        const_labels.len() - 1
+                    });
+                    format!("<.Lconst{num}{}>", c.get(3).map_or("", |m| m.as_str()))
+                });
+                *operands = Cow::Owned(new.into_owned());
+            }
+            if let Some(re) = &verbose_function_name_re {
+                if let Cow::Owned(new) = re.replace_all(operands, |c: &regex::Captures<'_>| {
+                    strip_hash(c.get(0).unwrap().as_str()).unwrap().to_owned()
+                }) {
+                    *operands = Cow::Owned(new);
+                }
+            }
+            if let Cow::Owned(new) = collapse_special_segments_in_operands(operands) {
+                *operands = Cow::Owned(new);
+            }
+            if canonicalize_hex {
+                let new =
+                    HEX_RE.replace_all(operands, |c: &regex::Captures<'_>| match c.name("addr") {
+                        Some(addr) => {
+                            format!("{}0x{} <", &c["lead"], addr.as_str().to_ascii_lowercase())
+                        }
+                        None => format!("0x{}", c["hex"].to_ascii_lowercase()),
+                    });
+                if let Cow::Owned(new) = new {
+                    *operands = Cow::Owned(new);
+                }
+            }
+            if let Some(re) = reg_re {
+                let new = re.replace_all(operands, |c: &regex::Captures<'_>| {
+                    let Some(reg) = c.name("reg") else { return c[0].to_owned() };
+                    let canonical = match reg.as_str() {
+                        "sb" => "r9",
+                        "sl" => "r10",
+                        "fp" if cx.arch_family == ArchFamily::Arm => "r11",
+                        "ip" => "r12",
+                        "zero" => "$0",
+                        "at" => "$1",
+                        "k0" => "$26",
+                        "k1" => "$27",
+                        "gp" => "$28",
+                        "sp" => "$29",
+                        "fp" | "s8" => "$30",
+                        "ra" => "$31",
+                        _ => unreachable!(),
+                    };
+                    canonical.to_owned()
+                });
+                if let Cow::Owned(new) = new {
+                    *operands = Cow::Owned(new);
+                }
             }
-            re.push_str(verbose_function_name);
         }
-        let re = Regex::new(&re).unwrap();
-        if let Cow::Owned(new) = re.replace_all(&cx.out, |c: &regex::Captures<'_>| {
-            c.get(0).unwrap().as_str().rsplit_once("::").unwrap().0.to_owned()
-        }) {
-            cx.out = new;
+        if let Cow::Owned(name) = collapse_special_segments(&function_name) {
+            function_name = Cow::Owned(name);
+        }
+        if let Some(parsed) = &mut cx.parsed {
+            let asm = lines
+                .iter()
+                .map(|line| match *line {
+                    Line::Inst { addr, name, ref operands, .. } => Asm::Instruction {
+                        address: addr,
+                        mnemonic: name.to_owned(),
+                        operands: operands.clone().into_owned(),
+                    },
+                    Line::Label { num } => Asm::Label { id: num },
+                })
+                .collect();
+            parsed.push((function_name.clone().into_owned(), asm));
+        }
+        check_forbidden_instructions(cx, &function_name, &lines);
+        if let Some(section) = section {
+            if last_section != Some(section) {
+                cx.out.push_str("; section ");
+                cx.out.push_str(section);
+                cx.out.push('\n');
+                last_section = Some(section);
+            }
         }
+        write_func(cx, &function_name, func_addr, &lines);
+        cx.function_names.push(function_name.into_owned());
     }
+    check_not_empty(cx);
+    check_expected_functions(cx);
     while cx.out.ends_with("\n\n") {
         cx.out.pop();
     }
 }
 
-fn write_func(cx: &mut RevisionContext<'_>, function_name: &str, lines: &[Line<'_>]) {
+/// Demangles the given name if it is a mangled Rust symbol, otherwise returns it as-is.
+///
+/// This is used for names that some versions of objdump do not demangle, so the name may
+/// already have been demangled by objdump.
+fn demangle(name: &str) -> Cow<'_, str> {
+    match rustc_demangle::try_demangle(name) {
+        Ok(demangled) => Cow::Owned(format!("{demangled:#}")),
+        Err(_) => Cow::Borrowed(name),
+    }
+}
+
+/// Returns the name of the symbol referred to by a relocation in the form used in the rest of
+/// the output.
+///
+/// Relocations against symbols in their own sections (rustc's default) usually refer to the
+/// section symbol (e.g., `.text._ZN7closure4call17h27d20dd906cbe2ccE`), which is not demangled
+/// by objdump.
+fn reloc_symbol(symbol: &str) -> Cow<'_, str> {
+    for prefix in [".text.unlikely.", ".text.", ".rodata.", ".data.rel.ro.", ".data.", ".bss."] {
+        let Some(name) = symbol.strip_prefix(prefix) else { continue };
+        if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+            return Cow::Owned(format!("{demangled:#}"));
+        }
+        // Compiler-internal labels for constants (e.g., `.rodata..Lanon.<hash>.0`).
+        if name.starts_with(".L") {
+            return Cow::Borrowed(name);
+        }
+        break;
+    }
+    Cow::Borrowed(strip_hash(symbol).unwrap_or(symbol))
+}
+
+/// Returns the name without the hash if the name is in the form of `path::to::fn::h[0-9a-f]{16}`.
+fn strip_hash(name: &str) -> Option<&str> {
+    let (name, hash) = name.rsplit_once("::")?;
+    (hash.len() == 17
+        && hash.as_bytes()[0] == b'h'
+        && hash.as_bytes()[1..].iter().all(|&b| b.is_ascii_digit() | matches!(b, b'a'..=b'f')))
+    .then_some(name)
+}
+
+/// Collapses special path segments in the legacy demangling format (e.g., `{{closure}}`,
+/// `{{vtable.shim}}`) into the form used by the v0 demangling format without the
+/// disambiguator (e.g., `{closure}`, `{vtable.shim}`).
+///
+/// These segments are emitted as-is by objdump's demangler even if the hash is stripped,
+/// and the doubled braces make the names hard to read, especially with nested closures.
+/// llvm-objdump doesn't decode the escapes in legacy symbol names, so the escaped form
+/// (`_$u7b$$u7b$closure$u7d$$u7d$`) is also collapsed:
+///
+///   asm_test::f::{{closure}}::{{closure}}  ->  asm_test::f::{closure}::{closure}
+fn collapse_special_segments(s: &str) -> Cow<'_, str> {
+    static SPECIAL_SEGMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new("::(\\{\\{|_\\$u7b\\$\\$u7b\\$)(?P<name>[a-z_.]+)(\\}\\}|\\$u7d\\$\\$u7d\\$)")
+            .unwrap()
+    });
+    SPECIAL_SEGMENT_RE.replace_all(s, "::{$name}")
+}
+
+/// Like [`collapse_special_segments`], but only applies to symbol references (`<..>`) in
+/// operands, to avoid rewriting other parts of operands (e.g., comments).
+fn collapse_special_segments_in_operands(s: &str) -> Cow<'_, str> {
+    let (Some(start), Some(end)) = (s.find('<'), s.rfind('>')) else { return Cow::Borrowed(s) };
+    if start > end {
+        return Cow::Borrowed(s);
+    }
+    match collapse_special_segments(&s[start..end]) {
+        Cow::Owned(sym) => Cow::Owned(format!("{}{sym}{}", &s[..start], &s[end..])),
+        Cow::Borrowed(_) => Cow::Borrowed(s),
+    }
+}
+
+fn check_forbidden_instructions(cx: &RevisionContext<'_>, function_name: &str, lines: &[Line<'_>]) {
+    let forbidden = cx
+        .tcx
+        .tester
+        .config
+        .forbidden_instructions
+        .iter()
+        .chain(&cx.revision.config.forbidden_instructions);
+    for (function_re, mnemonic) in forbidden {
+        if !function_re.is_match(function_name) {
+            continue;
+        }
+        for line in lines {
+            let Line::Inst { addr, name: mut inst, ref operands, .. } = *line else { continue };
+            if cx.arch_family == ArchFamily::X86 && is_x86_prefix(inst) {
+                // See write_func.
+                inst = operands.split_once(['\t', ' ']).map_or(operands, |(inst, _)| inst);
+            }
+            if inst == mnemonic {
+                panic!(
+                    "forbidden instruction `{mnemonic}` found in `{function_name}` at {addr:#x} (revision {})",
+                    cx.revision.name
+                );
+            }
+        }
+    }
+}
+
+fn check_not_empty(cx: &RevisionContext<'_>) {
+    if cx.function_names.is_empty() {
+        let msg = format!(
+            "no functions found in the assembly (revision {}); the crate may not emit code for its functions (e.g., all of them are generic or #[inline])",
+            cx.revision.name
+        );
+        if cx.tcx.tester.deny_empty_output {
+            panic!("{msg}");
+        }
+        eprintln!("warning: {msg}");
+    }
+}
+
+fn check_expected_functions(cx: &RevisionContext<'_>) {
+    let missing: Vec<_> = cx
+        .tcx
+        .tester
+        .config
+        .expected_functions
+        .iter()
+        .chain(&cx.revision.config.expected_functions)
+        .filter(|re| !cx.function_names.iter().any(|name| re.is_match(name)))
+        .map(Regex::as_str)
+        .collect();
+    if !missing.is_empty() {
+        panic!(
+            "no function matching the following regexes found (revision {}): {missing:?}",
+            cx.revision.name
+        );
+    }
+}
+
+/// Returns the pattern of branch targets (and other references to addresses in the current function).
+///
+/// `{f}` in the pattern is replaced with the (escaped) name of the current function. The `addr`
+/// group captures the absolute target address, and the `offset` group captures the offset from the
+/// start of the function for the `<{f}+0x..>` form. If neither participates in the match, the
+/// target is the start of the function.
+///
+/// The forms used by objdump differ in whether the target is annotated in a comment, and in the
+/// comment leader used for it:
+///
+/// | form                                | arch                                     |
+/// | ----------------------------------- | ---------------------------------------- |
+/// | `imm <f+0x..>`                      | most arches, including s390x and SPARC   |
+/// | `imm <f+0x..> @ imm = #..`          | Arm (LLVM)                               |
+/// | `imm\t// imm <f+0x..>`              | C-SKY                                    |
+/// | `imm\t# imm <f+0x..>`               | LoongArch (GNU)                          |
+/// | `.+imm \t; 0x.. <..>`               | AVR (the target is `pc + 2 + imm`)       |
+/// | `$+imm \t;abs 0x..`                 | MSP430                                   |
+fn label_pattern(cx: &RevisionContext<'_>) -> &'static str {
+    const COMMENT: &str =
+        "(-)?(0x)?[0-9a-f]+\t(//|#) (-)?(0x)?[0-9a-f]+ <{f}(\\+0x(?P<offset>[0-9a-f]+))?>";
+    match cx.arch_family {
+        ArchFamily::Arm if !cx.prefer_gnu => {
+            "(-)?(0x)?[0-9a-f]+ <{f}(\\+0x(?P<offset>[0-9a-f]+))?>( +@ imm = #(-)?0x[0-9a-f]+)?"
+        }
+        // Relative jumps always target the current section, so the target address in the
+        // comment can be used regardless of which symbol objdump chose to annotate it with.
+        ArchFamily::Avr => "\\.(?P<rel>(\\+|-)[0-9]+) +\t; 0x(?P<addr>[0-9a-f]+) <.*>",
+        ArchFamily::CSky => COMMENT,
+        ArchFamily::LoongArch if cx.prefer_gnu => COMMENT,
+        ArchFamily::Msp430 => "\\$(\\+|-)[0-9]+ +\t;abs 0x(?P<addr>[0-9a-f]+)",
+        // This also covers s390x, where GNU objdump prints targets of relative branches and
+        // address loads in the same form, without comment leader:
+        //   j	e <f+0xe>
+        //   brc	15,e <f+0xe>
+        //   brasl	%r14,a <f+0xa>
+        //   larl	%r1,12 <f+0x12>
+        _ => "(-)?(0x)?[0-9a-f]+ <{f}(\\+0x(?P<offset>[0-9a-f]+))?>",
+    }
+}
+
+/// Returns the absolute address of the branch target captured by the pattern returned by [`label_pattern`].
+///
+/// Returns `None` if the address in the comment doesn't match the relative offset encoded in
+/// the instruction at `inst_addr`, to avoid pointing at the wrong line if the address is
+/// computed differently (e.g., wrapped around for large offsets).
+fn label_addr(c: &regex::Captures<'_>, func_addr: u64, inst_addr: u64) -> Option<u64> {
+    let parse = |m: regex::Match<'_>| {
+        u64::from_str_radix(m.as_str(), 16).with_context(|| m.as_str().to_owned()).unwrap()
+    };
+    let addr = match c.name("addr") {
+        Some(addr) => parse(addr),
+        None => func_addr + c.name("offset").map_or(0, parse),
+    };
+    if let Some(rel) = c.name("rel") {
+        let rel = rel.as_str().parse::<i64>().unwrap();
+        if inst_addr.wrapping_add(2).wrapping_add_signed(rel) != addr {
+            return None;
+        }
+    }
+    Some(addr)
+}
+
+fn write_func(
+    cx: &mut RevisionContext<'_>,
+    function_name: &str,
+    func_addr: u64,
+    lines: &[Line<'_>],
+) {
     use core::fmt::Write as _;
+    const START_PAD: &str = "        ";
+    let show_bytes = cx.enabled(|c| c.show_bytes);
+    let show_addresses = cx.tcx.tester.show_addresses;
+    // The address column replaces the padding at the start of instruction lines, so that
+    // labels are not indented.
+    let start_pad = |addr: u64| {
+        if show_addresses {
+            Cow::Owned(format!("{:#6x}  ", addr - func_addr))
+        } else {
+            Cow::Borrowed(START_PAD)
+        }
+    };
+    if cx.tcx.tester.function_markers {
+        let _ = writeln!(cx.out, "; ---- {function_name} ----");
+    }
     let _ = writeln!(cx.out, "{function_name}:");
+    let mut lines = lines;
+    if cx.tcx.tester.trim_padding {
+        while let [rest @ .., Line::Inst { name, operands, .. }] = lines {
+            if !is_padding(cx, name, operands) {
+                break;
+            }
+            lines = rest;
+        }
+    }
+    let mut truncated = false;
+    if let Some(n) = cx.tcx.tester.head_instructions {
+        let len = head_len(lines, n);
+        truncated = len < lines.len();
+        lines = &lines[..len];
+    }
+    if cx.tcx.tester.bytes_only {
+        for line in lines {
+            if let Line::Inst { addr, bytes, .. } = line {
+                if !bytes.is_empty() {
+                    cx.out.push_str(&start_pad(*addr));
+                    push_collapsed_whitespace(&mut cx.out, bytes);
+                    cx.out.push('\n');
+                }
+            }
+        }
+        if truncated {
+            cx.out.push_str("        ; ... (truncated)\n");
+        }
+        cx.out.push('\n');
+        return;
+    }
     let mut instructions = lines.iter();
     while let Some(line) = instructions.next() {
-        const START_PAD: &str = "        ";
         fn inst_pad(len: usize) -> &'static str {
             // We use 18 bytes as inst+pad length for now. The instruction with the longest name on
             // x86_64 is probably vgf2p8affineinvqb (17 bytes), so this should be sufficient in most cases.
@@ -306,50 +1100,875 @@ fn write_func(cx: &mut RevisionContext<'_>, function_name: &str, lines: &[Line<'
             &MAX_INST_PAD[..cmp::max(MAX_INST_PAD.len().saturating_sub(len), 1)]
         }
         match *line {
-            Line::Inst { addr: _, name: inst, ref operands } => {
-                if cx.arch_family == ArchFamily::X86 && inst == "lock" {
+            Line::Inst { addr, name: inst, ref operands, ref bytes, jumps } => {
+                // Arrows drawn by --visualize-jumps are placed between the padding and the instruction.
+                let start_pad = if jumps.is_empty() {
+                    start_pad(addr)
+                } else {
+                    Cow::Owned(format!("{}{jumps}", start_pad(addr)))
+                };
+                // Prefixes are printed either as a separate instruction or with the prefixed
+                // instruction in the operands, depending on the objdump and the instruction:
+                //   lock
+                //   cmpxchg	qword ptr [rdi], rdx
+                //   rep		movsb	byte ptr es:[rdi], byte ptr [rsi]
+                // Render both as a single instruction.
+                if cx.arch_family == ArchFamily::X86 && is_x86_prefix(inst) {
+                    let prefix = inst;
                     if operands.is_empty() {
-                        if let Some(Line::Inst { addr: _, name: inst, operands }) =
-                            instructions.next()
+                        if let Some(Line::Inst {
+                            addr: _,
+                            name: inst,
+                            operands,
+                            bytes: next_bytes,
+                            jumps: _,
+                        }) = instructions.next()
                         {
-                            let inst_pad = inst_pad(inst.len() + 5);
-                            let _ = writeln!(cx.out, "{START_PAD}lock {inst}{inst_pad}{operands}");
+                            let inst_pad = inst_pad(prefix.len() + 1 + inst.len());
+                            let _ =
+                                write!(cx.out, "{start_pad}{prefix} {inst}{inst_pad}{operands}");
+                            if show_bytes {
+                                let _ = write!(cx.out, " ; {bytes} {next_bytes}");
+                            }
+                            cx.out.push('\n');
                             continue;
                         }
                     } else {
                         let (inst, operands) = operands.split_once('\t').unwrap_or((operands, ""));
                         if operands.is_empty() {
-                            let _ = writeln!(cx.out, "{START_PAD}lock {inst}");
+                            let _ = write!(cx.out, "{start_pad}{prefix} {inst}");
                         } else {
-                            let inst_pad = inst_pad(inst.len() + 5);
-                            let _ = writeln!(cx.out, "{START_PAD}lock {inst}{inst_pad}{operands}");
+                            let inst_pad = inst_pad(prefix.len() + 1 + inst.len());
+                            let _ =
+                                write!(cx.out, "{start_pad}{prefix} {inst}{inst_pad}{operands}");
                         }
+                        write_bytes(cx, show_bytes, bytes);
                         continue;
                     }
                 }
                 if operands.is_empty() {
-                    let _ = writeln!(cx.out, "{START_PAD}{inst}");
+                    let _ = write!(cx.out, "{start_pad}{inst}");
                 } else if cx.arch_family == ArchFamily::Hexagon {
+                    // Hexagon instructions are grouped into packets, and `inst` is the packet
+                    // start marker (`{`) for the first instruction of a packet and empty for
+                    // the others. The packet end marker and its annotations are part of the
+                    // operands of the last instruction, with irregular spacing:
+                    //   { 	r0 = memw_locked(r1)
+                    //     	p0 = cmp.eq(r0,r2)
+                    //     	if (!p0.new) jump:nt 0x20 }  :endloop0
                     if inst.is_empty() {
-                        let _ = writeln!(cx.out, "{START_PAD}  {operands}");
+                        let _ = write!(cx.out, "{start_pad}  ");
                     } else {
-                        assert_eq!(inst, "{");
-                        let _ = writeln!(cx.out, "{START_PAD}{{ {operands}");
+                        let _ = write!(cx.out, "{start_pad}{inst} ");
                     }
+                    push_collapsed_whitespace(&mut cx.out, operands);
+                } else if cx.arch_family == ArchFamily::Msp430 {
+                    // GNU objdump separates MSP430 operands and comments with tabs:
+                    //   mov	#4660,	r13	;#0x1234
+                    let inst_pad = inst_pad(inst.len());
+                    let _ = write!(cx.out, "{start_pad}{inst}{inst_pad}");
+                    push_collapsed_whitespace(&mut cx.out, operands);
                 } else {
                     let inst_pad = inst_pad(inst.len());
-                    let _ = writeln!(cx.out, "{START_PAD}{inst}{inst_pad}{operands}");
+                    let _ = write!(cx.out, "{start_pad}{inst}{inst_pad}{operands}");
                 }
+                write_bytes(cx, show_bytes, bytes);
             }
-            Line::Label { num } => {
-                let _ = writeln!(cx.out, "{num}:");
+            Line::Label { num } => match cx.tcx.tester.label_style {
+                LabelStyle::Numeric => {
+                    let _ = writeln!(cx.out, "{num}:");
+                }
+                LabelStyle::Dotted => {
+                    let _ = writeln!(cx.out, ".LBB{}_{num}:", cx.function_names.len());
+                }
+            },
+        }
+    }
+    if truncated {
+        cx.out.push_str("        ; ... (truncated)\n");
+    }
+    cx.out.push('\n');
+}
+
+/// Returns the demangled names (as printed by objdump) of the symbols that match `name`.
+fn resolve_symbol(cx: &RevisionContext<'_>, program: &str, name: &str) -> Vec<String> {
+    //   0000000000000000 g     F .text.foo	0000000000000004 .hidden foo
+    //                                      ^-- split_once('\t')
+    //                                                      ^-- split_once(' ')
+    fn names(s: &str) -> impl Iterator<Item = &str> {
+        s.lines().filter(|line| line.starts_with(|c: char| c.is_ascii_hexdigit())).filter_map(
+            |line| {
+                let (_, name) = line.split_once('\t')?.1.split_once(' ')?;
+                let name = [".hidden ", ".protected ", ".internal "]
+                    .iter()
+                    .find_map(|p| name.strip_prefix(p))
+                    .unwrap_or(name);
+                Some(name.trim_ascii())
+            },
+        )
+    }
+    let syms = |demangle: bool| {
+        let mut objdump = cx.tcx.docker_cmd(cx.obj_path.parent().unwrap(), None);
+        objdump.arg(program);
+        if demangle {
+            objdump.arg("-C");
+        }
+        objdump.arg("--syms").arg(&cx.obj_path);
+        objdump.deadline(cx.deadline);
+        let res = objdump.read_with_retry(cx.tcx.tester.docker_retries, &[DOCKER_ERROR_EXIT_CODE]);
+        cx.check_timeout(&res);
+        res.unwrap()
+    };
+    let (mangled, demangled) = (syms(false), syms(true));
+    let mut symbols: Vec<String> = vec![];
+    for (mangled, demangled) in names(&mangled).zip(names(&demangled)) {
+        let short = strip_hash(demangled).unwrap_or(demangled);
+        if (name == mangled
+            || name == demangled
+            || name == short
+            || name == collapse_special_segments(short))
+            && !symbols.iter().any(|s| s == demangled)
+        {
+            symbols.push(demangled.to_owned());
+        }
+    }
+    if symbols.is_empty() {
+        panic!(
+            "not found symbol `{name}` in {} (revision {})",
+            cx.obj_path.display(),
+            cx.revision.name
+        );
+    }
+    symbols
+}
+
+/// Returns the name of the section that contains the given position of the objdump output.
+fn section_before(s: &str, pos: usize) -> Option<&str> {
+    //   Disassembly of section .text.foo:
+    const PREFIX: &str = "\nDisassembly of section ";
+    let start = s[..pos].rfind(PREFIX)? + PREFIX.len();
+    let len = s[start..].find(":\n")?;
+    Some(&s[start..start + len])
+}
+
+/// Returns the number of lines up to and including the `n`-th instruction.
+fn head_len(lines: &[Line<'_>], n: usize) -> usize {
+    if n == 0 {
+        return 0;
+    }
+    let mut count = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if let Line::Inst { .. } = line {
+            count += 1;
+            if count == n {
+                return i + 1;
             }
         }
     }
+    lines.len()
+}
+
+/// Returns `true` if the given x86 instruction is a prefix that objdump can print separately
+/// from the prefixed instruction.
+fn is_x86_prefix(inst: &str) -> bool {
+    matches!(
+        inst,
+        "lock"
+            | "rep"
+            | "repe"
+            | "repz"
+            | "repne"
+            | "repnz"
+            | "data16"
+            | "data32"
+            | "addr32"
+            | "bnd"
+            | "notrack"
+            | "xacquire"
+            | "xrelease"
+            | "cs"
+            | "ds"
+            | "es"
+            | "fs"
+            | "gs"
+            | "ss"
+            | "rex"
+            | "rex64"
+    ) || inst.starts_with("rex.")
+}
+
+/// Returns `true` if the given instruction is used as alignment padding.
+fn is_padding(cx: &RevisionContext<'_>, inst: &str, operands: &str) -> bool {
+    // nop, c.nop (RISC-V), nopw/nopl (x86 AT&T syntax), nopr (s390x), etc.
+    if inst.starts_with("nop") || inst == "c.nop" {
+        return true;
+    }
+    match cx.arch_family {
+        // int3 is the fill of LLVM, and `cs`/`data16` are prefixes of multi-byte nop in GNU objdump:
+        //   cs nopw 0x0(%rax,%rax,1)
+        //   data16 cs nopw 0x0(%rax,%rax,1)
+        // `xchg ax, ax` is the 2-byte nop (`66 90`), printed without space by GNU objdump.
+        ArchFamily::X86 => {
+            inst == "int3"
+                || matches!(inst, "cs" | "data16")
+                    && operands.split_ascii_whitespace().any(|op| op.starts_with("nop"))
+                || inst == "xchg" && matches!(operands, "ax, ax" | "ax,ax")
+        }
+        // Zero fill.
+        ArchFamily::Arm | ArchFamily::AArch64 => inst == "udf" && matches!(operands, "#0" | "#0x0"),
+        _ => false,
+    }
+}
+
+/// Appends `s` with each run of whitespace collapsed into a single space.
+fn push_collapsed_whitespace(out: &mut String, s: &str) {
+    for (i, word) in s.split_ascii_whitespace().enumerate() {
+        if i != 0 {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+}
+
+/// Finishes the current instruction line, appending raw instruction bytes if requested.
+fn write_bytes(cx: &mut RevisionContext<'_>, show_bytes: bool, bytes: &str) {
+    if show_bytes && !bytes.is_empty() {
+        cx.out.push_str(" ; ");
+        cx.out.push_str(bytes);
+    }
     cx.out.push('\n');
 }
 
+/// A relocation shown by `-r`.
+struct Reloc<'a> {
+    offset: u64,
+    /// The address of the instruction the relocation applies to.
+    inst: u64,
+    kind: &'a str,
+    symbol: &'a str,
+    addend: i64,
+    /// The address of the target if it is in the function.
+    local: Option<u64>,
+}
+
+/// Returns `true` if the relocation is an x86-64 PC-relative one, whose addend is relative to
+/// the relocated field instead of the end of the instruction (e.g., `-0x4` for calls).
+fn x86_64_pc_relative(kind: &str) -> bool {
+    matches!(
+        kind.strip_prefix("R_X86_64_"),
+        Some(
+            "PC8"
+                | "PC16"
+                | "PC32"
+                | "PC64"
+                | "PLT32"
+                | "GOTPCREL"
+                | "GOTPCRELX"
+                | "REX_GOTPCRELX"
+                | "CODE_4_GOTPCRELX"
+                | "GOTTPOFF"
+                | "CODE_4_GOTTPOFF"
+                | "TLSGD"
+                | "TLSLD"
+                | "GOTPC32_TLSDESC"
+                | "CODE_4_GOTPC32_TLSDESC"
+        )
+    )
+}
+
 enum Line<'a> {
-    Inst { addr: u64, name: &'a str, operands: Cow<'a, str> },
+    Inst { addr: u64, name: &'a str, operands: Cow<'a, str>, bytes: Cow<'a, str>, jumps: &'a str },
     Label { num: u32 },
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use cargo_config2::TargetTripleRef;
+
+    use super::*;
+    use crate::{Docker, Revision, Tester, TesterContext};
+
+    fn tcx(tester: &Tester) -> TesterContext<'_> {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/basic");
+        let docker = Docker { path: "docker".into(), user: None };
+        TesterContext::new(tester, &manifest_dir, None, docker)
+    }
+
+    /// Runs `handle_asm` on `raw` as if it were the output of objdump for `target`.
+    fn handle(tester: &Tester, target: &str, raw: &str) -> String {
+        handle_with(tester, target, None, raw)
+    }
+
+    /// Like [`handle`], but as if it were the output of GNU objdump.
+    fn handle_gnu(tester: &Tester, target: &str, raw: &str) -> String {
+        handle_with(tester, target, Some(true), raw)
+    }
+
+    fn handle_with(tester: &Tester, target: &str, prefer_gnu: Option<bool>, raw: &str) -> String {
+        handle_revision(tester, &Revision::new("test", target), prefer_gnu, raw)
+    }
+
+    fn handle_revision(
+        tester: &Tester,
+        revision: &Revision,
+        prefer_gnu: Option<bool>,
+        raw: &str,
+    ) -> String {
+        let tcx = tcx(tester);
+        let target = TargetTripleRef::from(&revision.target);
+        let mut cx = RevisionContext::new(&tcx, revision, &target);
+        // See disassemble.
+        cx.prefer_gnu = prefer_gnu.unwrap_or(matches!(
+            cx.arch_family,
+            ArchFamily::Avr | ArchFamily::CSky | ArchFamily::Mips | ArchFamily::Msp430
+        ));
+        handle_asm(&mut cx, raw);
+        cx.out
+    }
+
+    #[test]
+    fn avr_relative_branch() {
+        // The comment of a relative branch is annotated with the nearest symbol, which may be
+        // an unrelated absolute symbol such as `__zero_reg__` instead of the function.
+        let raw = "
+count.o:     file format elf32-avr
+
+
+Disassembly of section .text.count:
+
+00000000 <count>:
+   0:\t80 e0       \tldi\tr24, 0x00\t; 0
+   2:\t8f 5f       \tsubi\tr24, 0xFF\t; 255
+   4:\t8a 30       \tcpi\tr24, 0x0A\t; 10
+   6:\te9 f7       \tbrne\t.-6      \t; 0x2 <__zero_reg__+0x1>
+   8:\t01 c0       \trjmp\t.+2      \t; 0xc <count+0xc>
+   a:\t80 e0       \tldi\tr24, 0x00\t; 0
+   c:\t08 95       \tret
+";
+        let expected = "\
+count:
+        ldi               r24, 0x00\t; 0
+0:
+        subi              r24, 0xFF\t; 255
+        cpi               r24, 0x0A\t; 10
+        brne              0b
+        rjmp              1f
+        ldi               r24, 0x00\t; 0
+1:
+        ret
+";
+        assert_eq!(handle(&Tester::new(), "avr-none", raw), expected);
+    }
+
+    #[test]
+    fn avr_long_branch() {
+        // Branches near the limit of rjmp (-4096..=4094), and a branch whose target address in
+        // the comment is wrapped around the flash size and doesn't match the encoded offset.
+        let raw = "
+00000000 <spin>:
+   0:\tff c7       \trjmp\t.+4094   \t; 0x1000 <spin+0x1000>
+   2:\tfe cf       \trjmp\t.-4      \t; 0x1ffe <spin+0x1ffe>
+   4:\t00 00       \tnop
+ 1000:\t00 c8       \trjmp\t.-4096   \t; 0x2 <spin+0x2>
+ 1ffe:\t08 95       \tret
+";
+        let expected = "\
+spin:
+        rjmp              1f
+0:
+        rjmp              .-4      \t; 0x1ffe <spin+0x1ffe>
+        nop
+1:
+        rjmp              0b
+        ret
+";
+        assert_eq!(handle(&Tester::new(), "avr-none", raw), expected);
+    }
+
+    #[test]
+    fn mips_r6() {
+        // Compact branches of release 6 have no delay slot, and their targets are printed in
+        // the same form as other branches.
+        let raw = "
+mips.o:     file format elf32-tradbigmips
+
+
+Disassembly of section .text.f:
+
+00000000 <f>:
+   0:\t00001025 \tmove\t$2,$0
+   4:\t2484ffff \taddiu\t$4,$4,-1
+   8:\tf89ffffe \tbnezc\t$4,4 <f+0x4>
+   c:\t24420001 \taddiu\t$2,$2,1
+  10:\tc8000001 \tbc\t18 <f+0x18>
+  14:\t00000000 \tnop
+  18:\td81f0000 \tjrc\t$31
+";
+        let expected = "\
+f:
+        move              $2,$0
+0:
+        addiu             $4,$4,-1
+        bnezc             $4,0b
+        addiu             $2,$2,1
+        bc                1f
+        nop
+1:
+        jrc               $31
+";
+        assert_eq!(handle(&Tester::new(), "mipsisa32r6-unknown-linux-gnu", raw), expected);
+    }
+
+    #[test]
+    fn msp430_continued_bytes() {
+        // GNU objdump for MSP430 wraps the raw bytes of long instructions to the next line
+        // without the instruction.
+        let raw = "
+add.o:     file format elf32-msp430
+
+
+Disassembly of section .text.store:
+
+00000000 <store>:
+   0:\tb2 40 5a 5a \tmov\t#23130,\t&0x0120\t;#0x5a5a
+   4:\t20 01 
+   6:\t1f 53       \tinc\tr15\t\t
+   8:\t3f 90 0a 00 \tcmp\t#10,\tr15\t;#0x000a
+   c:\tfc 23       \tjnz\t$-6      \t;abs 0x6
+   e:\t30 41       \tret\t\t\t
+";
+        let expected = "\
+store:
+        mov               #23130, &0x0120 ;#0x5a5a ; b2 40 5a 5a 20 01
+0:
+        inc               r15 ; 1f 53
+        cmp               #10, r15 ;#0x000a ; 3f 90 0a 00
+        jnz               0b ; fc 23
+        ret ; 30 41
+";
+        assert_eq!(handle(&Tester::new().show_bytes(), "msp430-none-elf", raw), expected);
+    }
+
+    #[test]
+    fn msp430_no_tab() {
+        // Instructions without a tab after the raw bytes. Words that look like hex bytes are
+        // only treated as bytes if the whole line consists of them or they are followed by
+        // padding.
+        let raw = "
+00000000 <f>:
+   0:\tbc r15
+   2:\t0f 4e  mov r14, r15
+   4:\t30 41
+";
+        let expected = "\
+f:
+        bc                r15
+        mov               r14, r15 ; 0f 4e 30 41
+";
+        assert_eq!(handle(&Tester::new().show_bytes(), "msp430-none-elf", raw), expected);
+    }
+
+    #[test]
+    fn continuation_lines() {
+        // Only lines indented with tabs like the instruction column are continuations; lines
+        // printed as is (e.g., source lines added by `-S`) are ignored.
+        let raw = "
+0000000000000000 <f>:
+    let x = a + b;
+       0: 8d 04 37                     \tlea\teax, [rdi + rsi]
+       3: 01 02 03 04                  \t.byte\t0x01, 0x02,
+                                       \t0x03, 0x04
+    x
+       7: c3                           \tret
+";
+        let expected = "\
+f:
+        lea               eax, [rdi + rsi]
+        .byte             0x01, 0x02, 0x03, 0x04
+        ret
+";
+        assert_eq!(handle(&Tester::new(), "x86_64-unknown-linux-gnu", raw), expected);
+    }
+
+    #[test]
+    fn hexagon_packets() {
+        // Output of llvm-objdump 22. Instructions in a packet are either on separate lines or
+        // on the same line separated by `;`, and the packet end may be followed by a loop end
+        // marker.
+        let raw = "
+lib.o:\tfile format elf32-hexagon
+
+Disassembly of section .text.sum:
+
+00000000 <sum>:
+       0:\t10 40 01 60\t60014010 { \tloop0(0x8,r1)
+       4:\t02 c0 00 78\t7800c002   \tr2 = #0x0 }
+       8:\t23 c0 80 9b\t9b80c023 { \tr3 = memw(r0++#0x4) }
+       c:\t02 83 02 f3\tf3028302 { \tr2 = add(r2,r3)
+      10:\t00 c0 00 7f\t7f00c000   \tnop }  :endloop0
+      14:\tc0 3f 20 50\t50203fc0 { \tr0 = r2; \tjumpr r31 }
+
+Disassembly of section .text.copy:
+
+00000000 <copy>:
+       0:\t03 00 12 80\t80120003 { \tr2 = memw(r1+#0x0); \tmemw(r0+#0x0) = r3 }  :mem_noshuf
+       4:\t00 c0 9f 52\t529fc000 { \tjumpr r31 }
+";
+        let expected = "\
+sum:
+        { loop0(0x8,r1)
+          r2 = #0x0 }
+        { r3 = memw(r0++#0x4) }
+        { r2 = add(r2,r3)
+          nop } :endloop0
+        { r0 = r2
+          jumpr r31 }
+
+copy:
+        { r2 = memw(r1+#0x0)
+          memw(r0+#0x0) = r3 } :mem_noshuf
+        { jumpr r31 }
+";
+        assert_eq!(handle(&Tester::new(), "hexagon-unknown-linux-musl", raw), expected);
+    }
+
+    #[test]
+    fn hexagon_unusual_lines() {
+        // Lines that don't have the usual shape are passed through instead of panicking: a
+        // function ending with a loop end marker, and a line only with the encoding.
+        let raw = "
+00000000 <spin>:
+       0:\t08 40 00 69\t69004008 { \tloop0(0x0,#0x1) }
+       4:\t00 c0 00 7f\t7f00c000 { \tnop }  :endloop0
+       8:\t00 c0 00 7f\t7f00c000
+";
+        let expected = "\
+spin:
+        { loop0(0x0,#0x1) }
+        { nop } :endloop0
+";
+        assert_eq!(handle(&Tester::new(), "hexagon-unknown-linux-musl", raw), expected);
+    }
+
+    #[test]
+    fn x86_prefixes() {
+        // llvm-objdump prints some prefixes as a separate instruction, and others with the
+        // prefixed instruction in the operands.
+        let raw = "
+0000000000000000 <f>:
+       0: f0                           \tlock
+       1: 48 0f b1 17                  \tcmpxchg\tqword ptr [rdi], rdx
+       5: f3 a4                        \trep\t\tmovsb\tbyte ptr es:[rdi], byte ptr [rsi]
+       7: c3                           \tret
+";
+        let expected = "\
+f:
+        lock cmpxchg      qword ptr [rdi], rdx ; f0 48 0f b1 17
+        rep movsb         byte ptr es:[rdi], byte ptr [rsi] ; f3 a4
+        ret ; c3
+";
+        assert_eq!(handle(&Tester::new().show_bytes(), "x86_64-unknown-linux-gnu", raw), expected);
+    }
+
+    #[test]
+    fn x86_gnu() {
+        // GNU objdump prints prefixes with the prefixed instruction, addresses of branch
+        // targets without `0x` prefix, and wraps the bytes of long instructions.
+        let raw = "
+0000000000000000 <f>:
+   0:\tf0 48 0f b1 17       \tlock cmpxchg QWORD PTR [rdi],rdx
+   5:\tf3 a4                \trep movs BYTE PTR es:[rdi],BYTE PTR ds:[rsi]
+   7:\tb8 ff 00 00 00       \tmov    eax,0XFF
+   c:\t85 c0                \ttest   eax,eax
+   e:\t75 05                \tjne    15 <f+0x15>
+  10:\t48 8d 35 00 00 00 00 \tlea    rsi,[rip+0x0]        # 17 <g+0x7>
+  15:\tc3                   \tret
+  16:\tcc                   \tint3
+  17:\t66 90                \txchg   ax,ax
+  19:\t66 2e 0f 1f 84 00 00 \tcs nop WORD PTR [rax+rax*1+0x0]
+  20:\t00 00 00 
+";
+        let expected = "\
+f:
+        lock cmpxchg QWORD PTR [rdi],rdx
+        rep movs BYTE PTR es:[rdi],BYTE PTR ds:[rsi]
+        mov               eax,0XFF
+        test              eax,eax
+        jne               0f
+        lea               rsi,[rip+0x0]        # 17 <g+0x7>
+0:
+        ret
+";
+        let tester = Tester::new().trim_padding();
+        assert_eq!(handle_gnu(&tester, "x86_64-unknown-linux-gnu", raw), expected);
+        let expected = "\
+f:
+        lock cmpxchg QWORD PTR [rdi],rdx
+        rep movs BYTE PTR es:[rdi],BYTE PTR ds:[rsi]
+        mov               eax,0xff
+        test              eax,eax
+        jne               0f
+        lea               rsi,[rip+0x0]        # 0x17 <g+0x7>
+0:
+        ret
+        int3
+        xchg              ax,ax
+        cs nop WORD PTR [rax+rax*1+0x0]
+";
+        let tester = Tester::new().canonicalize_hex();
+        assert_eq!(handle_gnu(&tester, "x86_64-unknown-linux-gnu", raw), expected);
+    }
+
+    #[test]
+    fn relocations() {
+        // Placeholder targets of relocated instructions are replaced with the relocation
+        // targets instead of getting labels.
+        let tester = Tester::new().resolve_relocations();
+        let raw = "
+0000000000000000 <closure::nested::hf6f0cf621fa4aabb>:
+       0: 89 fa                        \tmov\tedx, edi
+       2: 48 8d 35 00 00 00 00         \tlea\trsi, [rip]              # 0x9 <closure::nested::hf6f0cf621fa4aabb+0x9>
+\t\t0000000000000005:  R_X86_64_PC32\t.text._ZN7closure6nested28_$u7b$$u7b$closure$u7d$$u7d$17hf51e758f296862b2E-0x4
+       9: c7 05 00 00 00 00 05 00 00 00\tmov\tdword ptr [rip], 0x5    # 0x13 <closure::nested::hf6f0cf621fa4aabb+0x13>
+\t\t000000000000000b:  R_X86_64_PC32\t.rodata..Lanon.fad58de7366495db4650cfefac2fcd61.0
+      13: e9 00 00 00 00               \tjmp\t0x18 <closure::nested::hf6f0cf621fa4aabb+0x18>
+\t\t0000000000000014:  R_X86_64_PLT32\tcore::panicking::panic::h27d20dd906cbe2cc-0x4
+";
+        let expected = "\
+closure::nested:
+        mov               edx, edi
+        lea               rsi, [rip]              # <closure::nested::{closure}>
+        mov               dword ptr [rip], 0x5    # <.Lconst0+0x8>
+        jmp               <core::panicking::panic>
+";
+        assert_eq!(handle(&tester, "x86_64-unknown-linux-gnu", raw), expected);
+        // GNU objdump shows relocations after the rest of the bytes of long instructions.
+        let raw = "
+0000000000000000 <f>:
+   0:\te8 00 00 00 00       \tcall   5 <f+0x5>
+\t\t\t1: R_X86_64_PLT32\tg-0x4
+   5:\tc7 05 00 00 00 00 05 \tmov    DWORD PTR [rip+0x0],0x5        # f <f+0xf>
+   c:\t00 00 00 
+\t\t\t7: R_X86_64_PC32\t.data
+   f:\tc3                   \tret
+";
+        let expected = "\
+f:
+        call              <g>
+        mov               DWORD PTR [rip+0x0],0x5        # <.data+0x8>
+        ret
+";
+        assert_eq!(handle_gnu(&tester, "x86_64-unknown-linux-gnu", raw), expected);
+        // Without Tester::resolve_relocations, the placeholders are handled like other targets.
+        let expected = "\
+f:
+        call              0f
+0:
+        mov               DWORD PTR [rip+0x0],0x5        # 1f
+1:
+        ret
+";
+        assert_eq!(handle_gnu(&Tester::new(), "x86_64-unknown-linux-gnu", raw), expected);
+        // References are appended to relocated instructions without them.
+        let raw = "
+0000000000000000 <f>:
+       0: 90000001     \tadrp\tx1, 0x0 <f>
+\t\t0000000000000000:  R_AARCH64_ADR_PREL_PG_HI21\t.rodata..Lanon.fad58de7366495db4650cfefac2fcd61.0+0x10
+       4: 91000021     \tadd\tx1, x1, #0x0
+\t\t0000000000000004:  R_AARCH64_ADD_ABS_LO12_NC\t.rodata..Lanon.fad58de7366495db4650cfefac2fcd61.0+0x10
+       8: 14000000     \tb\t0x8 <f+0x8>
+\t\t0000000000000008:  R_AARCH64_JUMP26\t.text._RNvCs3upHQdE93J4_7generic6nested
+";
+        let expected = "\
+f:
+        adrp              x1, <.Lconst0+0x10>
+        add               x1, x1, #0x0 <.Lconst0+0x10>
+        b                 <generic::nested>
+";
+        assert_eq!(handle(&tester, "aarch64-unknown-linux-gnu", raw), expected);
+        // Relocations against the function itself target local labels.
+        let raw = "
+Disassembly of section .text.f:
+
+00000000 <f>:
+   0:\t8a 95       \tdec\tr24
+   2:\tf9 f7       \tbrne\t.-2      \t; 0x2 <f+0x2>
+\t\t\t2: R_AVR_7_PCREL\t.text.f
+   4:\tf9 f3       \tbreq\t.-2      \t; 0x4 <f+0x4>
+\t\t\t4: R_AVR_7_PCREL\t.text.f+0x8
+   6:\tff cf       \trjmp\t.-2      \t; 0x6 <f+0x6>
+\t\t\t6: R_AVR_13_PCREL\t.text.f
+   8:\t08 95       \tret
+";
+        let expected = "\
+f:
+0:
+        dec               r24
+        brne              0b
+        breq              1f
+        rjmp              0b
+1:
+        ret
+";
+        assert_eq!(handle(&tester, "avr-none", raw), expected);
+    }
+
+    #[test]
+    fn only_symbol_order() {
+        // Functions are sorted by their names without the hash, and then by their contents.
+        let raw = "
+0000000000000000 <generic::f::h0000000000000001>:
+       0: 8d 47 02                     \tlea\teax, [rdi + 0x2]
+       3: c3                           \tret
+
+0000000000000000 <generic::e::h0000000000000002>:
+       0: c3                           \tret
+
+0000000000000000 <generic::f::h0000000000000003>:
+       0: 8d 47 01                     \tlea\teax, [rdi + 0x1]
+       3: c3                           \tret
+";
+        let expected = "\
+generic::e:
+        ret
+
+generic::f:
+        lea               eax, [rdi + 0x1]
+        ret
+
+generic::f:
+        lea               eax, [rdi + 0x2]
+        ret
+";
+        let revision = Revision::new("test", "x86_64-unknown-linux-gnu").only_symbol("generic::");
+        assert_eq!(handle_revision(&Tester::new(), &revision, None, raw), expected);
+    }
+
+    #[test]
+    #[should_panic = "unresolved reference to `f` itself found at 0x0"]
+    fn strict_labels() {
+        // A branch into the middle of an instruction.
+        let raw = "
+0000000000000000 <f>:
+       0: eb 01                        \tjmp\t0x3 <f+0x3>
+       2: b8 00 00 00 00               \tmov\teax, 0x0
+       7: c3                           \tret
+";
+        handle(&Tester::new().strict_labels(), "x86_64-unknown-linux-gnu", raw);
+    }
+
+    #[test]
+    fn label_patterns() {
+        // Arm (llvm-objdump)
+        let raw = "
+00000000 <f>:
+       0: e3500000     \tcmp\tr0, #0
+       4: 0a000000     \tbeq\t0xc <f+0xc>             @ imm = #0x0
+       8: eafffffc     \tb\t0x0 <f>                 @ imm = #-0x10
+       c: e12fff1e     \tbx\tlr
+";
+        let expected = "\
+f:
+0:
+        cmp               r0, #0
+        beq               1f
+        b                 0b
+1:
+        bx                lr
+";
+        assert_eq!(handle(&Tester::new(), "armv7-unknown-linux-gnueabihf", raw), expected);
+        // LoongArch (GNU)
+        let raw = "
+0000000000000000 <f>:
+   0:\t02bffc84 \taddi.w      \t$a0, $a0, -1
+   4:\t5ffffc80 \tbne         \t$a0, $zero, -4\t# 0 <f>
+   8:\t4c000020 \tret
+";
+        let expected = "\
+f:
+0:
+        addi.w            $a0, $a0, -1
+        bne               $a0, $zero, 0b
+        ret
+";
+        assert_eq!(handle_gnu(&Tester::new(), "loongarch64-unknown-linux-gnu", raw), expected);
+        // C-SKY
+        let raw = "
+00000000 <f>:
+   0:\t2000      \taddi      \tr0, 1
+   2:\t6c03      \tbnez      \tr0, 0x6\t// 6 <f+0x6>
+   4:\t2000      \taddi      \tr0, 1
+   6:\t783c      \trts
+";
+        let expected = "\
+f:
+        addi              r0, 1
+        bnez              r0, 0f
+        addi              r0, 1
+0:
+        rts
+";
+        assert_eq!(handle(&Tester::new(), "csky-unknown-linux-gnuabiv2", raw), expected);
+    }
+
+    #[test]
+    fn padding() {
+        let cases: &[(&str, &str, &str, bool)] = &[
+            ("x86_64-unknown-linux-gnu", "int3", "", true),
+            ("x86_64-unknown-linux-gnu", "nop", "word ptr cs:[rax + rax]", true),
+            ("x86_64-unknown-linux-gnu", "nopw", "%cs:0x0(%rax,%rax,1)", true),
+            ("x86_64-unknown-linux-gnu", "data16", "cs nopw 0x0(%rax,%rax,1)", true),
+            ("x86_64-unknown-linux-gnu", "xchg", "ax, ax", true),
+            ("x86_64-unknown-linux-gnu", "cs", "mov eax, dword ptr [rax]", false),
+            ("x86_64-unknown-linux-gnu", "ret", "", false),
+            ("aarch64-unknown-linux-gnu", "udf", "#0x0", true),
+            ("aarch64-unknown-linux-gnu", "udf", "#0x1", false),
+            ("aarch64-unknown-linux-gnu", "int3", "", false),
+            ("riscv64gc-unknown-linux-gnu", "c.nop", "", true),
+        ];
+        let tester = Tester::new();
+        let tcx = tcx(&tester);
+        for &(target, inst, operands, expected) in cases {
+            let revision = Revision::new("test", target);
+            let target = TargetTripleRef::from(&revision.target);
+            let cx = RevisionContext::new(&tcx, &revision, &target);
+            assert_eq!(
+                is_padding(&cx, inst, operands),
+                expected,
+                "{}: {inst} {operands}",
+                cx.target_name
+            );
+        }
+    }
+
+    #[test]
+    fn canonicalize_registers() {
+        let raw = "
+00000000 <f>:
+   0:\te92d4800 \tpush\t{fp, lr}
+   4:\te1a0c00d \tmov\tip, sp
+   8:\te8bd8800 \tpop\t{fp, pc}
+";
+        let tester = Tester::new().canonicalize_registers();
+        let expected = "\
+f:
+        push              {r11, lr}
+        mov               r12, sp
+        pop               {r11, pc}
+";
+        assert_eq!(handle(&tester, "armv7-unknown-linux-gnueabihf", raw), expected);
+        let raw = "
+00000000 <f>:
+   0:\t03e00008 \tjr\t$ra
+   4:\t27bd0010 \taddiu\t$sp,$sp,16
+";
+        let expected = "\
+f:
+        jr                $31
+        addiu             $29,$29,16
+";
+        assert_eq!(handle(&tester, "mipsisa32r6-unknown-linux-gnu", raw), expected);
+    }
+}
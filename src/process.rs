@@ -3,11 +3,17 @@
 use alloc::{
     format,
     string::{String, ToString as _},
+    vec::Vec,
 };
-use core::{fmt, str};
+use core::{fmt, str, time::Duration};
 use std::{
+    eprintln,
     ffi::OsStr,
-    process::{Command, ExitStatus, Output, Stdio},
+    io::{self, BufRead as _},
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::mpsc,
+    thread,
+    time::Instant,
 };
 
 use anyhow::{Context as _, Error, Result};
@@ -26,11 +32,12 @@ macro_rules! cmd {
 #[must_use]
 pub(crate) struct ProcessBuilder {
     cmd: Command,
+    deadline: Option<Instant>,
 }
 
 impl ProcessBuilder {
     pub(crate) fn from_std(cmd: Command) -> Self {
-        Self { cmd }
+        Self { cmd, deadline: None }
     }
 
     pub(crate) fn into_std(self) -> Command {
@@ -55,17 +62,28 @@ impl ProcessBuilder {
         self
     }
 
+    /// Removes a variable from the process's environment.
+    pub(crate) fn env_remove(&mut self, key: impl AsRef<OsStr>) -> &mut Self {
+        self.cmd.env_remove(key.as_ref());
+        self
+    }
+
     pub(crate) fn stdin(&mut self, cfg: Stdio) -> &mut Self {
         self.cmd.stdin(cfg);
         self
     }
 
+    /// Kills the process if it doesn't exit by the given deadline.
+    pub(crate) fn deadline(&mut self, deadline: Option<Instant>) -> &mut Self {
+        self.deadline = deadline;
+        self
+    }
+
     /// Executes a process, waiting for completion, and mapping non-zero exit
     /// status to an error.
     pub(crate) fn run(&mut self) -> Result<()> {
-        let status = self.cmd.status().with_context(|| {
-            process_error(format!("could not execute process {self}"), None, None)
-        })?;
+        let status = self.status();
+        let status = self.check_launch(status)?;
         if status.success() {
             Ok(())
         } else {
@@ -80,9 +98,23 @@ impl ProcessBuilder {
     /// Executes a process, captures its stdio output, returning the captured
     /// output, or an error if non-zero exit status.
     pub(crate) fn run_with_output(&mut self) -> Result<Output> {
-        let output = self.cmd.output().with_context(|| {
-            process_error(format!("could not execute process {self}"), None, None)
-        })?;
+        let output = self.output();
+        self.check_output(output)
+    }
+
+    fn check_launch<T>(&self, res: io::Result<T>) -> Result<T> {
+        match res {
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                Err(Error::new(e).context(format!("process {self} timed out")))
+            }
+            res => res.with_context(|| {
+                process_error(format!("could not execute process {self}"), None, None)
+            }),
+        }
+    }
+
+    fn check_output(&self, output: io::Result<Output>) -> Result<Output> {
+        let output = self.check_launch(output)?;
         if output.status.success() {
             Ok(output)
         } else {
@@ -97,7 +129,102 @@ impl ProcessBuilder {
     /// Executes a process, captures its stdio output, returning the captured
     /// standard output as a `String`.
     pub(crate) fn read(&mut self) -> Result<String> {
-        let mut output = String::from_utf8(self.run_with_output()?.stdout)
+        let output = self.run_with_output()?;
+        self.output_to_string(output)
+    }
+
+    /// Executes a process like [`read`](Self::read), but calls `on_line` for each line of
+    /// the standard output as it arrives.
+    pub(crate) fn read_with_progress(&mut self, mut on_line: impl FnMut(&str)) -> Result<String> {
+        let child = self.cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn();
+        let mut child = self.check_launch(child)?;
+        let stderr = read_to_end(child.stderr.take());
+        let (tx, rx) = mpsc::channel();
+        let stdout = io::BufReader::new(child.stdout.take().unwrap());
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut stdout = String::new();
+        loop {
+            let line = match self.deadline {
+                Some(deadline) => {
+                    match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                        Ok(line) => line,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                        Err(mpsc::RecvTimeoutError::Timeout) => {
+                            let _ = child.kill();
+                            let _ = child.wait();
+                            let res = Err(io::ErrorKind::TimedOut.into());
+                            return self.check_launch(res);
+                        }
+                    }
+                }
+                None => match rx.recv() {
+                    Ok(line) => line,
+                    Err(mpsc::RecvError) => break,
+                },
+            };
+            on_line(&line);
+            stdout.push_str(&line);
+            stdout.push('\n');
+        }
+        let status = match self.deadline {
+            Some(deadline) => wait_with_deadline(&mut child, deadline),
+            None => child.wait(),
+        };
+        let status = self.check_launch(status)?;
+        let output = Output { status, stdout: stdout.into_bytes(), stderr: stderr.join().unwrap() };
+        let output = self.check_output(Ok(output))?;
+        self.output_to_string(output)
+    }
+
+    /// Executes a process like [`read`](Self::read), but retries up to `retries`
+    /// times with backoff if the process exited with one of `retry_codes`.
+    ///
+    /// Errors on launching the process are not retried.
+    pub(crate) fn read_with_retry(&mut self, retries: u32, retry_codes: &[i32]) -> Result<String> {
+        let mut attempt = 0;
+        let output = loop {
+            let output = self.output();
+            let retryable = output.as_ref().is_ok_and(|output| {
+                output.status.code().is_some_and(|code| retry_codes.contains(&code))
+            });
+            if !retryable || attempt == retries {
+                break output;
+            }
+            attempt += 1;
+            eprintln!("process {self} failed; retrying ({attempt}/{retries})");
+            thread::sleep(Duration::from_secs(1 << attempt));
+        };
+        let output = self.check_output(output)?;
+        self.output_to_string(output)
+    }
+
+    fn status(&mut self) -> io::Result<ExitStatus> {
+        let Some(deadline) = self.deadline else { return self.cmd.status() };
+        let mut child = self.cmd.spawn()?;
+        wait_with_deadline(&mut child, deadline)
+    }
+
+    fn output(&mut self) -> io::Result<Output> {
+        let Some(deadline) = self.deadline else { return self.cmd.output() };
+        let mut child = self.cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+        // Read pipes in separate threads to prevent the child from blocking on a full pipe.
+        let stdout = read_to_end(child.stdout.take());
+        let stderr = read_to_end(child.stderr.take());
+        // On timeout, the reader threads are detached, because grandchildren (e.g., rustc
+        // spawned by cargo) may still hold the pipes.
+        let status = wait_with_deadline(&mut child, deadline)?;
+        Ok(Output { status, stdout: stdout.join().unwrap(), stderr: stderr.join().unwrap() })
+    }
+
+    fn output_to_string(&self, output: Output) -> Result<String> {
+        let mut output = String::from_utf8(output.stdout)
             .with_context(|| format!("failed to parse output from {self}"))?;
         while output.ends_with('\n') || output.ends_with('\r') {
             output.pop();
@@ -106,6 +233,29 @@ impl ProcessBuilder {
     }
 }
 
+fn read_to_end(r: Option<impl io::Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    let mut r = r.unwrap();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = r.read_to_end(&mut buf);
+        buf
+    })
+}
+
+fn wait_with_deadline(child: &mut Child, deadline: Instant) -> io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
 // Based on https://github.com/rust-lang/cargo/blob/0.47.0/src/cargo/util/process_builder.rs
 impl fmt::Display for ProcessBuilder {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
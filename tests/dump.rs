@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// These tests run the whole pipeline, including the container engine, so they are ignored by
+// default. Run them with `cargo test --test dump -- --ignored`.
+
+use std::path::Path;
+
+use asmtest::{Revision, Tester};
+
+#[test]
+#[ignore = "requires docker or podman"]
+fn basic() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/basic");
+    Tester::new().dump(manifest_dir, "asm", &[Revision::new("x86_64", "x86_64-unknown-linux-gnu")]);
+}
+
+#[test]
+#[ignore = "requires docker or podman"]
+fn no_mangle() {
+    // Symbols of `#[no_mangle]` functions are not mangled, so their names must be kept as is.
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ffi");
+    Tester::new().dump(manifest_dir, "asm", &[
+        Revision::new("x86_64", "x86_64-unknown-linux-gnu"),
+        Revision::new("aarch64", "aarch64-unknown-linux-gnu"),
+    ]);
+}
+
+#[test]
+#[ignore = "requires docker or podman"]
+fn naked() {
+    // Naked functions can contain data that objdump can't decode as instructions.
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/naked");
+    Tester::new().dump(manifest_dir, "asm", &[
+        Revision::new("x86_64", "x86_64-unknown-linux-gnu"),
+        Revision::new("aarch64", "aarch64-unknown-linux-gnu"),
+    ]);
+}
+
+#[test]
+#[ignore = "requires docker or podman"]
+fn ctor() {
+    // Constructors registered via `.init_array` (like `#[ctor]`) and functions placed in
+    // `.text.startup` are in their own sections, but they must be dumped like other functions.
+    // They refer to a static, whose placeholder address in the relocatable object must not be
+    // left as a reference to the function itself (checked by strict_labels).
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ctor");
+    Tester::new().strict_labels().dump(manifest_dir, "asm", &[
+        Revision::new("x86_64", "x86_64-unknown-linux-gnu"),
+        Revision::new("aarch64", "aarch64-unknown-linux-gnu"),
+    ]);
+}
+
+#[test]
+#[ignore = "requires docker or podman"]
+fn closure() {
+    // With legacy mangling, names of closures contain special path segments (`{{closure}}`),
+    // and they can be nested.
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/closure");
+    let tester = Tester::new()
+        .strict_labels()
+        .codegen_args(["-Z unstable-options", "-C symbol-mangling-version=legacy"]);
+    tester.dump(manifest_dir, "asm", &[
+        Revision::new("x86_64", "x86_64-unknown-linux-gnu"),
+        Revision::new("aarch64", "aarch64-unknown-linux-gnu"),
+    ]);
+}
+
+#[test]
+#[ignore = "requires docker or podman"]
+fn generic() {
+    // With v0 mangling, names of instances of generic functions contain generic arguments
+    // (e.g., `<generic::Wrap<generic::Wrap<u32>> as core::ops::arith::Add>::add`).
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/generic");
+    let tester = Tester::new().strict_labels().codegen_args(["-C symbol-mangling-version=v0"]);
+    tester.dump(manifest_dir, "asm", &[
+        Revision::new("x86_64", "x86_64-unknown-linux-gnu"),
+        Revision::new("aarch64", "aarch64-unknown-linux-gnu"),
+    ]);
+}
@@ -0,0 +1,7 @@
+basic::load_acquire:
+        mov               eax, dword ptr [rdi]
+        ret
+
+basic::add:
+        lea               eax, [rdi + rsi]
+        ret
@@ -0,0 +1,15 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#![no_std]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+#[inline(never)]
+pub fn add(a: u32, b: u32) -> u32 {
+    a.wrapping_add(b)
+}
+
+#[inline(never)]
+pub fn load_acquire(a: &AtomicU32) -> u32 {
+    a.load(Ordering::Acquire)
+}
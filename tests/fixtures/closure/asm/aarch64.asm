@@ -0,0 +1,38 @@
+closure::call:
+        mov               x3, x1
+        mov               w1, w2
+        br                x3
+
+closure::nested:
+0:
+        mov               w2, w0
+        adrp              x1, 0b
+        add               x1, x1, #0x0
+        mov               w0, #0x1                // =1
+1:
+        b                 1f
+
+closure::nested::{closure}:
+0:
+        sub               sp, sp, #0x20
+        stp               x29, x30, [sp, #0x10]
+        add               x29, sp, #0x10
+        add               x8, sp, #0x4
+        str               w1, [sp, #0x4]
+        add               x0, sp, #0x8
+        str               x8, [sp, #0x8]
+        adrp              x8, 0b
+        add               x8, x8, #0x0
+        add               w2, w1, #0x1
+        mov               x1, x8
+1:
+        bl                1f
+        ldp               x29, x30, [sp, #0x10]
+        add               sp, sp, #0x20
+        ret
+
+closure::nested::{closure}::{closure}:
+        ldr               x8, [x0]
+        ldr               w8, [x8]
+        mul               w0, w8, w1
+        ret
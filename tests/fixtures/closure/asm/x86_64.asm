@@ -0,0 +1,31 @@
+closure::call:
+        mov               rax, rsi
+        mov               esi, edx
+        jmp               rax
+
+closure::nested:
+        mov               edx, edi
+        lea               rsi, [rip]              # 0f
+0:
+        mov               edi, 0x1
+        jmp               0x13 <closure::nested+0x13>
+
+closure::nested::{closure}:
+        sub               rsp, 0x18
+        mov               dword ptr [rsp + 0xc], esi
+        lea               rax, [rsp + 0xc]
+        mov               qword ptr [rsp + 0x10], rax
+        lea               edx, [rsi + 0x1]
+        lea               rsi, [rip]              # 0f
+0:
+        lea               rdi, [rsp + 0x10]
+        call              1f
+1:
+        add               rsp, 0x18
+        ret
+
+closure::nested::{closure}::{closure}:
+        mov               eax, esi
+        mov               rcx, qword ptr [rdi]
+        imul              eax, dword ptr [rcx]
+        ret
@@ -0,0 +1,13 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#![no_std]
+
+#[inline(never)]
+fn call(f: &dyn Fn(u32) -> u32, x: u32) -> u32 {
+    f(x)
+}
+
+#[inline(never)]
+pub fn nested(x: u32) -> u32 {
+    call(&|a| call(&|b| b.wrapping_mul(a), a.wrapping_add(1)), x)
+}
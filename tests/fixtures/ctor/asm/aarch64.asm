@@ -0,0 +1,19 @@
+ctor::init_startup:
+0:
+        adrp              x8, 0f
+        mov               w9, #42
+        ldr               x8, [x8]
+        str               w9, [x8]
+        ret
+
+ctor::INIT::init:
+        stp               x29, x30, [sp, #-16]!
+        mov               x29, sp
+0:
+        adrp              x1, 0f
+        mov               w0, #1
+        ldr               x1, [x1]
+1:
+        bl                1f
+        ldp               x29, x30, [sp], #16
+        ret
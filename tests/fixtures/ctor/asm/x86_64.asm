@@ -0,0 +1,11 @@
+ctor::init_startup:
+        mov               rax, qword ptr [rip]    # 0f
+0:
+        mov               dword ptr [rax], 42
+        ret
+
+ctor::INIT::init:
+        mov               rax, qword ptr [rip]    # 0f
+0:
+        lock inc          dword ptr [rax]
+        ret
@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#![no_std]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+pub static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+// Like `#[ctor]`: a function called by the loader, registered via `.init_array`.
+#[used]
+#[link_section = ".init_array"]
+static INIT: extern "C" fn() = {
+    extern "C" fn init() {
+        COUNTER.fetch_add(1, Ordering::Relaxed);
+    }
+    init
+};
+
+// A constructor with the GNU `.text.startup` convention.
+#[used]
+#[link_section = ".init_array"]
+static INIT_STARTUP: extern "C" fn() = init_startup;
+
+#[link_section = ".text.startup"]
+extern "C" fn init_startup() {
+    COUNTER.store(42, Ordering::Relaxed);
+}
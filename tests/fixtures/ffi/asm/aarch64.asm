@@ -0,0 +1,22 @@
+ffi_add:
+        add               w0, w1, w0
+        ret
+
+ffi_collatz_steps:
+        cmp               x0, #2
+        b.lo              1f
+        mov               w8, wzr
+0:
+        lsr               x9, x0, #1
+        add               x10, x0, x0, lsl #1
+        tst               x0, #0x1
+        add               w8, w8, #1
+        csinc             x0, x9, x10, eq
+        cmp               x0, #1
+        b.hi              0b
+        mov               w0, w8
+        ret
+1:
+        mov               w8, wzr
+        mov               w0, w8
+        ret
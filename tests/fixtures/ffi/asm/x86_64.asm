@@ -0,0 +1,22 @@
+ffi_add:
+        lea               eax, [rdi + rsi]
+        ret
+
+ffi_collatz_steps:
+        xor               eax, eax
+        cmp               rdi, 2
+        jb                1f
+        xor               eax, eax
+        nop               word ptr [rax + rax]
+0:
+        mov               ecx, edi
+        lea               rdx, [rdi + 2*rdi]
+        inc               rdx
+        shr               rdi
+        test              cl, 1
+        cmovne            rdi, rdx
+        inc               eax
+        cmp               rdi, 1
+        ja                0b
+1:
+        ret
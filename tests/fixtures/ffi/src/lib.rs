@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#![no_std]
+
+#[no_mangle]
+pub extern "C" fn ffi_add(a: u32, b: u32) -> u32 {
+    a.wrapping_add(b)
+}
+
+#[no_mangle]
+pub extern "C" fn ffi_collatz_steps(mut n: u64) -> u32 {
+    let mut steps = 0;
+    while n > 1 {
+        n = if n % 2 == 0 { n / 2 } else { n.wrapping_mul(3).wrapping_add(1) };
+        steps += 1;
+    }
+    steps
+}
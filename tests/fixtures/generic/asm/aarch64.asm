@@ -0,0 +1,30 @@
+generic::sum::<generic::Wrap<generic::Wrap<u32>>, 2>:
+        stp               x29, x30, [sp, #-32]!
+        str               x19, [sp, #16]
+        mov               x29, sp
+        mov               x19, x0
+        mov               w0, wzr
+        mov               w1, w19
+0:
+        bl                0f
+        lsr               x1, x19, #32
+        ldr               x19, [sp, #16]
+        ldp               x29, x30, [sp], #32
+1:
+        b                 1f
+
+<generic::Wrap<generic::Wrap<u32>> as core::ops::arith::Add>::add:
+0:
+        b                 0f
+
+<generic::Wrap<u32> as core::ops::arith::Add>::add:
+        add               w0, w1, w0
+        ret
+
+nested:
+0:
+        b                 0f
+
+nested_sum:
+0:
+        b                 0f
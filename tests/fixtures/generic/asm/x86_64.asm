@@ -0,0 +1,31 @@
+generic::sum::<generic::Wrap<generic::Wrap<u32>>, 2>:
+        push              r14
+        push              rbx
+        push              rax
+        mov               rbx, rdi
+        mov               r14, qword ptr [rip]    # 0f
+0:
+        xor               edi, edi
+        mov               esi, ebx
+        call              r14
+        shr               rbx, 32
+        mov               edi, eax
+        mov               esi, ebx
+        mov               rax, r14
+        add               rsp, 8
+        pop               rbx
+        pop               r14
+        jmp               rax
+
+<generic::Wrap<generic::Wrap<u32>> as core::ops::arith::Add>::add:
+        jmp               qword ptr [rip]         # 0x6 <<generic::Wrap<generic::Wrap<u32>> as core::ops::arith::Add>::add+0x6>
+
+<generic::Wrap<u32> as core::ops::arith::Add>::add:
+        lea               eax, [rdi + rsi]
+        ret
+
+nested:
+        jmp               qword ptr [rip]         # 0x6 <nested+0x6>
+
+nested_sum:
+        jmp               0x5 <nested_sum+0x5>
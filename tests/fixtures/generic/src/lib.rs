@@ -0,0 +1,34 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#![no_std]
+
+use core::ops::Add;
+
+pub struct Wrap<T>(pub T);
+
+impl<T: Add<Output = T>> Add for Wrap<T> {
+    type Output = Self;
+    #[inline(never)]
+    fn add(self, rhs: Self) -> Self {
+        Wrap(self.0 + rhs.0)
+    }
+}
+
+#[inline(never)]
+fn sum<T: Add<Output = T>, const N: usize>(a: [T; N], init: T) -> T {
+    let mut acc = init;
+    for x in a {
+        acc = acc + x;
+    }
+    acc
+}
+
+#[no_mangle]
+pub fn nested(a: u32, b: u32) -> u32 {
+    (Wrap(Wrap(a)) + Wrap(Wrap(b))).0.0
+}
+
+#[no_mangle]
+pub fn nested_sum(a: [Wrap<Wrap<u32>>; 2]) -> u32 {
+    sum(a, Wrap(Wrap(0))).0.0
+}
@@ -0,0 +1,4 @@
+naked::naked_with_data:
+        mov               w0, #0x2a               // =42
+        ret
+        .word             0xffffffff
@@ -0,0 +1,6 @@
+naked::naked_with_data:
+        mov               eax, 0x2a
+        ret
+        ud2
+        <unknown>
+        <unknown>
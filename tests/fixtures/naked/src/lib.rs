@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#![no_std]
+
+use core::arch::naked_asm;
+
+// Data after the return doesn't decode as valid instructions.
+#[unsafe(naked)]
+pub extern "C" fn naked_with_data() -> u32 {
+    #[cfg(target_arch = "x86_64")]
+    naked_asm!("mov eax, 42", "ret", ".byte 0x0f, 0x0b, 0xff, 0xff, 0xff");
+    #[cfg(target_arch = "aarch64")]
+    naked_asm!("mov w0, #42", "ret", ".byte 0xff, 0xff, 0xff, 0xff");
+}